@@ -0,0 +1,127 @@
+//! Headless simulation runner for batch experiments.
+//!
+//! Runs the engine for a fixed number of ticks without a window, then writes the final state
+//! as a material view PNG, a thermal view PNG and a binary save file.
+
+use anyhow::{bail, Context, Result};
+use image::RgbaImage;
+use mintage::render::render_rgba8;
+use mintage::sim::{build_world_and_engine, load_config, load_dbs, world_size, AssetPaths, DEFAULT_SEED};
+use mintage::world::World;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+const DEFAULT_TICKS: u64 = 1000;
+const DEFAULT_OUT_DIR: &str = "out";
+const DEFAULT_THERMAL_VIEW_RANGE: f32 = 500.0;
+
+const USAGE: &str = "\
+Usage: mintage-sim [options]
+
+Options:
+  --config <path>      Engine config              (default: assets/config.ron)
+  --materials <path>   Material definitions       (default: assets/materials_base.ron)
+  --reactions <path>   Reaction definitions       (default: assets/reactions_base.ron)
+  --map <path>         Initial map bitmap         (default: assets/map.png)
+  --map-key <path>     Map color key              (default: assets/map_key.ron)
+  --no-map             Start from an empty world instead of the map
+  --load <path>        Continue from a save file instead of the map
+  --ticks <n>          Number of ticks to run     (default: 1000)
+  --seed <n>           Base RNG seed              (default: 123456789)
+  --out <dir>          Output directory           (default: out)
+  -h, --help           Print this help
+
+Writes <out>/materials.png, <out>/thermal.png and <out>/world.sav.";
+
+struct Args {
+    paths: AssetPaths,
+    load: Option<String>,
+    ticks: u64,
+    seed: u64,
+    out: String,
+}
+
+fn parse_args() -> Result<Option<Args>> {
+    let mut args = Args {
+        paths: AssetPaths::default(),
+        load: None,
+        ticks: DEFAULT_TICKS,
+        seed: DEFAULT_SEED,
+        out: DEFAULT_OUT_DIR.to_owned(),
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(flag) = iter.next() {
+        if flag == "-h" || flag == "--help" {
+            return Ok(None);
+        }
+        if flag == "--no-map" {
+            args.paths.map = None;
+            continue;
+        }
+        let value = iter.next().with_context(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--config" => args.paths.config = value,
+            "--materials" => args.paths.materials = value,
+            "--reactions" => args.paths.reactions = value,
+            "--map" => args.paths.map = Some(value),
+            "--map-key" => args.paths.map_key = value,
+            "--load" => args.load = Some(value),
+            "--ticks" => args.ticks = value.parse().with_context(|| format!("invalid tick count '{}'", value))?,
+            "--seed" => args.seed = value.parse().with_context(|| format!("invalid seed '{}'", value))?,
+            "--out" => args.out = value,
+            _ => bail!("unknown option '{}'\n\n{}", flag, USAGE),
+        }
+    }
+    Ok(Some(args))
+}
+
+fn write_png(path: &Path, w: usize, h: usize, rgba: Vec<u8>) -> Result<()> {
+    let img = RgbaImage::from_raw(w as u32, h as u32, rgba).context("image buffer does not match world size")?;
+    img.save(path).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let Some(args) = parse_args()? else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    let config = load_config(&args.paths.config)?;
+    let (w, h) = world_size(&config)?;
+    let thermal_view_range = config.get("thermal_view_range")
+        .and_then(|v| v.as_f64())
+        .map_or(DEFAULT_THERMAL_VIEW_RANGE, |v| v as f32);
+
+    let (mat_db, react_db) = load_dbs(&args.paths)?;
+    let (mut world, mut phys_eng) = build_world_and_engine(config, w, h, &mat_db, &react_db, &args.paths, args.seed)?;
+    if let Some(save_path) = &args.load {
+        world = World::load(save_path, &mat_db, &react_db, &mut phys_eng)?;
+    }
+
+    println!("World {}x{}, seed {}, starting at tick {}", w, h, args.seed, world.tick_count);
+    println!("Material hash {:016x}, reaction hash {:016x}", mat_db.content_hash(), react_db.content_hash());
+
+    let start = Instant::now();
+    for _ in 0..args.ticks {
+        phys_eng.step(&mut world);
+    }
+    let secs = start.elapsed().as_secs_f64();
+    println!("Ran {} ticks in {:.2}s ({:.1} TPS)", args.ticks, secs, args.ticks as f64 / secs.max(f64::EPSILON));
+
+    let out = Path::new(&args.out);
+    fs::create_dir_all(out).with_context(|| format!("failed to create output directory {}", out.display()))?;
+
+    let mat_ids = &world.cell_mat_ids.cur;
+    let temps = &world.cell_temps.cur;
+    write_png(&out.join("materials.png"), w, h, render_rgba8(mat_ids, temps, &mat_db, None))?;
+    write_png(&out.join("thermal.png"), w, h, render_rgba8(mat_ids, temps, &mat_db, Some(thermal_view_range)))?;
+
+    let save_path = out.join("world.sav");
+    world.save(&phys_eng, &save_path.to_string_lossy())?;
+
+    println!("Wrote {}", out.display());
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! Material painting tools for the visualizer.
+//!
+//! Every edit is sent to the Sim thread as a `WorldCommand`, so painted cells go through the
+//! same intent pipeline as everything else and behave exactly like cells loaded from a map.
+
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use macroquad::prelude::*;
+use mintage::material::{MaterialDb, MaterialId};
+use mintage::physics::command::{Region, WorldCommand};
+use mintage::sim::Shared;
+use crate::to_mq_color;
+
+const MAX_RADIUS: usize = 32;
+const DEFAULT_PAINT_TEMP: f32 = 50.0;
+
+/// Temperature added per sim tick while heating (right-click) or removed while cooling (shift + right-click).
+const HEAT_PER_TICK: f32 = 10.0;
+
+/// Palette layout, in screen pixels.
+const PALETTE_X: f32 = 10.0;
+const PALETTE_Y: f32 = 24.0 * 10.0;
+const PALETTE_ROW: f32 = 22.0;
+const PALETTE_W: f32 = 200.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BrushShape {
+    Circle,
+    Square,
+}
+
+pub struct Brush {
+    pub shape: BrushShape,
+    pub radius: usize,
+    pub selected: MaterialId,
+    pub temp: f32,
+    pub erase: bool,
+
+    /// Cell painted last frame, used to fill gaps when the mouse moves quickly.
+    last_cell: Option<(usize, usize)>,
+    /// Sim tick up to which heat has been sent, while heating.
+    last_heat_tick: Option<u64>,
+}
+
+impl Brush {
+    pub fn new(mat_db: &MaterialDb) -> Self {
+        Self {
+            shape: BrushShape::Circle,
+            radius: 2,
+            selected: mat_db.get_id("base:water").unwrap_or_default(),
+            temp: DEFAULT_PAINT_TEMP,
+            erase: false,
+            last_cell: None,
+            last_heat_tick: None,
+        }
+    }
+
+    pub fn region(&self, x: usize, y: usize) -> Region {
+        match self.shape {
+            BrushShape::Circle => Region::Circle { cx: x, cy: y, r: self.radius },
+            BrushShape::Square => Region::square(x, y, self.radius),
+        }
+    }
+
+    /// Keyboard controls for brush settings.
+    pub fn handle_keys(&mut self, mat_db: &MaterialDb) {
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        if is_key_pressed(KeyCode::RightBracket) {
+            self.radius = (self.radius + 1).min(MAX_RADIUS);
+        }
+        if is_key_pressed(KeyCode::LeftBracket) {
+            self.radius = self.radius.saturating_sub(1);
+        }
+        if is_key_pressed(KeyCode::B) {
+            self.shape = match self.shape {
+                BrushShape::Circle => BrushShape::Square,
+                BrushShape::Square => BrushShape::Circle,
+            };
+        }
+        if is_key_pressed(KeyCode::E) {
+            self.erase = !self.erase;
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            let count = mat_db.get_mat_count().max(1) as u16;
+            let step = if shift { count - 1 } else { 1 };
+            self.selected = MaterialId((self.selected.0 + step) % count);
+            self.erase = false;
+        }
+
+        let temp_step = if shift { 100.0 } else { 10.0 };
+        if is_key_pressed(KeyCode::Up) {
+            self.temp += temp_step;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.temp -= temp_step;
+        }
+    }
+
+    /// Paint or heat at `cell` (the cell under the cursor, if any) based on mouse buttons.
+    pub fn handle_mouse(&mut self, shared: &Arc<Shared>, cell: Option<(usize, usize)>) {
+        let painting = is_mouse_button_down(MouseButton::Left);
+        let heating = is_mouse_button_down(MouseButton::Right);
+        let Some(cell) = cell.filter(|_| painting || heating) else {
+            self.last_cell = None;
+            self.last_heat_tick = None;
+            return;
+        };
+
+        if painting {
+            let from = self.last_cell.unwrap_or(cell);
+            for (x, y) in stroke_points(from, cell, self.radius.max(1)) {
+                let region = self.region(x, y);
+                let cmd = if self.erase {
+                    WorldCommand::Clear { region }
+                } else {
+                    WorldCommand::PlaceMaterial { region, mat: self.selected, temp: Some(self.temp) }
+                };
+                shared.send_command(cmd);
+            }
+            self.last_heat_tick = None;
+        } else {
+            // Heat stacks, so send the heat for every tick since the last frame in one command
+            // on the cursor. The rate is then the same whatever the frame rate or sim speed.
+            let tick = shared.tick_count.load(Ordering::Relaxed);
+            let ticks = tick.saturating_sub(self.last_heat_tick.unwrap_or(tick));
+            if (ticks > 0) {
+                let cooling = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+                let delta = HEAT_PER_TICK * ticks as f32;
+                shared.send_command(WorldCommand::AddTemp { region: self.region(cell.0, cell.1), delta: if cooling { -delta } else { delta } });
+            }
+            self.last_heat_tick = Some(tick);
+        }
+        self.last_cell = Some(cell);
+    }
+
+    /// Draw the material palette and select a material on click. Returns true if the mouse is
+    /// over the palette, in which case the world should not be painted.
+    pub fn draw_palette(&mut self, mat_db: &MaterialDb) -> bool {
+        let (mx, my) = mouse_position();
+        let count = mat_db.get_mat_count();
+        let hovered = (PALETTE_X..PALETTE_X + PALETTE_W).contains(&mx)
+            && (PALETTE_Y..PALETTE_Y + PALETTE_ROW * count as f32).contains(&my);
+
+        for i in 0..count {
+            let id = MaterialId(i as u16);
+            let Some(mat) = mat_db.get(id) else { continue };
+            let y = PALETTE_Y + PALETTE_ROW * i as f32;
+
+            if (hovered && (y..y + PALETTE_ROW).contains(&my) && is_mouse_button_pressed(MouseButton::Left)) {
+                self.selected = id;
+                self.erase = false;
+            }
+
+            draw_rectangle(PALETTE_X, y + 2.0, 18.0, 18.0, to_mq_color(mat.color));
+            if (id == self.selected && !self.erase) {
+                draw_rectangle_lines(PALETTE_X - 2.0, y, 22.0, 22.0, 2.0, YELLOW);
+            }
+            draw_text(&mat.name, PALETTE_X + 26.0, y + 17.0, 20.0, WHITE);
+        }
+        hovered
+    }
+
+    pub fn status_text(&self, mat_db: &MaterialDb) -> String {
+        let shape = match self.shape {
+            BrushShape::Circle => "Circle",
+            BrushShape::Square => "Square",
+        };
+        let tool = if self.erase {
+            "Eraser".to_owned()
+        } else {
+            mat_db.get(self.selected).map_or_else(String::new, |m| m.name.clone())
+        };
+        format!("Brush: {} {} r={}  Paint Temp: {:.0}°C", tool, shape, self.radius, self.temp)
+    }
+}
+
+/// Brush centers from `from` to `to`, spaced at most `spacing` cells apart, so a fast mouse
+/// drag paints a continuous line. Excludes `from`, which was painted on the previous frame,
+/// unless the two are the same cell.
+fn stroke_points(from: (usize, usize), to: (usize, usize), spacing: usize) -> Vec<(usize, usize)> {
+    let dx = to.0 as isize - from.0 as isize;
+    let dy = to.1 as isize - from.1 as isize;
+    let dist = dx.abs().max(dy.abs()) as usize;
+    if dist == 0 {
+        return vec![to];
+    }
+    let steps = dist.div_ceil(spacing);
+    (1..=steps)
+        .map(|i| {
+            let t = i as f32 / steps as f32;
+            let x = from.0 as f32 + dx as f32 * t;
+            let y = from.1 as f32 + dy as f32 * t;
+            (x.round() as usize, y.round() as usize)
+        })
+        .collect()
+}
@@ -0,0 +1,145 @@
+//! Pan and zoom for the world view.
+
+use macroquad::prelude::*;
+
+/// Zoom multiplier per mouse wheel notch.
+const ZOOM_STEP: f32 = 1.15;
+/// Maximum zoom, in screen pixels per cell.
+const MAX_ZOOM: f32 = 64.0;
+/// How far out the view can zoom, relative to the zoom that fits the whole world.
+const MIN_ZOOM_OF_FIT: f32 = 0.5;
+
+/// Maps world cells to screen pixels. Cell (x, y) covers the screen rect starting at
+/// `offset + (x, y) * zoom` with side `zoom`.
+pub struct WorldCamera {
+    /// World size in cells.
+    w: usize,
+    h: usize,
+
+    /// Screen pixels per cell.
+    pub zoom: f32,
+    /// Screen position of the world's top-left corner.
+    pub offset: Vec2,
+
+    /// True until the user pans or zooms. While set, the view refits on window resize.
+    fitted: bool,
+    /// Mouse position last frame while drag-panning.
+    drag_from: Option<Vec2>,
+}
+
+impl WorldCamera {
+    pub fn new(w: usize, h: usize, screen: Vec2) -> Self {
+        let mut camera = Self { w, h, zoom: 1.0, offset: Vec2::ZERO, fitted: true, drag_from: None };
+        camera.fit(screen);
+        camera
+    }
+
+    fn fit_zoom(&self, screen: Vec2) -> f32 {
+        (screen.x / self.w as f32).min(screen.y / self.h as f32)
+    }
+
+    /// Show the whole world, centered in the window.
+    pub fn fit(&mut self, screen: Vec2) {
+        self.zoom = self.fit_zoom(screen);
+        self.offset = (screen - self.world_size() * self.zoom) * 0.5;
+        self.fitted = true;
+    }
+
+    /// World size in cells.
+    fn world_size(&self) -> Vec2 {
+        vec2(self.w as f32, self.h as f32)
+    }
+
+    /// World size in screen pixels at the current zoom.
+    pub fn dest_size(&self) -> Vec2 {
+        self.world_size() * self.zoom
+    }
+
+    /// Zoom by `factor`, keeping the world point under `anchor` (a screen position) fixed.
+    pub fn zoom_at(&mut self, anchor: Vec2, factor: f32, screen: Vec2) {
+        let min_zoom = (self.fit_zoom(screen) * MIN_ZOOM_OF_FIT).min(1.0);
+        let zoom = (self.zoom * factor).clamp(min_zoom, MAX_ZOOM);
+        let world_pos = (anchor - self.offset) / self.zoom;
+        self.offset = anchor - world_pos * zoom;
+        self.zoom = zoom;
+        self.fitted = false;
+    }
+
+    pub fn pan(&mut self, delta: Vec2) {
+        self.offset += delta;
+        self.fitted = false;
+    }
+
+    /// Screen position of the top-left corner of cell (x, y).
+    pub fn cell_to_screen(&self, x: f32, y: f32) -> Vec2 {
+        self.offset + vec2(x, y) * self.zoom
+    }
+
+    /// Cell under a screen position, if it is inside the world.
+    pub fn screen_to_cell(&self, pos: Vec2) -> Option<(usize, usize)> {
+        let p = (pos - self.offset) / self.zoom;
+        if (p.x < 0.0 || p.y < 0.0) {
+            return None;
+        }
+        let (x, y) = (p.x as usize, p.y as usize);
+        if (x < self.w && y < self.h) { Some((x, y)) } else { None }
+    }
+
+    /// Mouse wheel zoom, middle-drag pan and `R` to reset the view.
+    pub fn handle_input(&mut self) {
+        let screen = vec2(screen_width(), screen_height());
+        let mouse = Vec2::from(mouse_position());
+
+        let (_, wheel_y) = mouse_wheel();
+        if wheel_y != 0.0 {
+            self.zoom_at(mouse, ZOOM_STEP.powf(wheel_y.signum()), screen);
+        }
+
+        if is_mouse_button_down(MouseButton::Middle) {
+            if let Some(from) = self.drag_from {
+                self.pan(mouse - from);
+            }
+            self.drag_from = Some(mouse);
+        } else {
+            self.drag_from = None;
+        }
+
+        if (is_key_pressed(KeyCode::R) || self.fitted) {
+            self.fit(screen);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camera_fit_and_mapping() {
+        let camera = WorldCamera::new(100, 50, vec2(400.0, 400.0));
+        assert_eq!(camera.zoom, 4.0);
+        assert_eq!(camera.offset, vec2(0.0, 100.0));
+
+        assert_eq!(camera.screen_to_cell(vec2(0.0, 100.0)), Some((0, 0)));
+        assert_eq!(camera.screen_to_cell(vec2(399.0, 299.0)), Some((99, 49)));
+        assert_eq!(camera.screen_to_cell(vec2(10.0, 99.0)), None);
+        assert_eq!(camera.screen_to_cell(vec2(10.0, 300.0)), None);
+        assert_eq!(camera.cell_to_screen(2.0, 3.0), vec2(8.0, 112.0));
+    }
+
+    #[test]
+    fn test_camera_zoom_keeps_anchor_fixed() {
+        let screen = vec2(400.0, 400.0);
+        let mut camera = WorldCamera::new(100, 50, screen);
+        let anchor = vec2(123.0, 234.0);
+        let cell = camera.screen_to_cell(anchor);
+
+        camera.zoom_at(anchor, 2.0, screen);
+        assert_eq!(camera.zoom, 8.0);
+        assert_eq!(camera.screen_to_cell(anchor), cell);
+
+        // Panning moves the world with the mouse.
+        camera.pan(vec2(-8.0, 0.0));
+        assert_eq!(camera.screen_to_cell(anchor), cell.map(|(x, y)| (x + 1, y)));
+    }
+}
@@ -0,0 +1,194 @@
+mod brush;
+mod camera;
+
+use std::sync::atomic::Ordering;
+use macroquad::prelude::*;
+use mintage::clock::SimControl;
+use mintage::render::thermal_view_color;
+use mintage::sim::{AssetPaths, DEFAULT_SEED, TpsTracker, load_config, spawn_sim_thread, world_size};
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "mintage 0.1.0".to_owned(),
+        window_width: 2400,
+        window_height: 1400,
+        fullscreen: false,
+        ..Default::default()
+    }
+}
+
+/// Converts a simulation color to a macroquad color.
+pub fn to_mq_color(c: mintage::material::Color) -> Color {
+    Color::new(c.r, c.g, c.b, c.a)
+}
+
+#[macroquad::main(window_conf)]
+async fn main() {
+
+    // Load config from RON file.
+    let paths = AssetPaths::default();
+    let config = load_config(&paths.config).unwrap();
+
+    // World size in cells.
+    let (w, h) = world_size(&config).unwrap();
+
+    // Thermal view temp range set in config.
+    let thermal_view_range = config.get("thermal_view_range").expect("Missing config: thermal_view_range")
+        .as_f64().expect("Invalid config: thermal_view_range must be f64") as f32;
+
+    // Spawn Sim thread, hold on to shared state.
+    let shared = spawn_sim_thread(config, w, h, paths, DEFAULT_SEED).unwrap();
+
+    // Tracks ticks per second.
+    let mut tps_tracker = TpsTracker::new();
+
+    // Render loop.
+    let mut img = Image::gen_image_color(w as u16, h as u16, BLACK);
+    let tex = Texture2D::from_image(&img);
+    tex.set_filter(FilterMode::Nearest);
+
+    let mut view_thermal = false;
+    let mut brush = brush::Brush::new(&shared.mat_db);
+    let mut camera = camera::WorldCamera::new(w, h, vec2(screen_width(), screen_height()));
+
+    loop {
+        // Toggle view mode.
+        if is_key_pressed(KeyCode::Space) {
+            view_thermal = !view_thermal;
+        }
+
+        // Sim clock controls.
+        let clock = **shared.clock.load();
+        if is_key_pressed(KeyCode::P) {
+            shared.send_control(SimControl::TogglePause);
+        }
+        if is_key_pressed(KeyCode::Period) {
+            shared.send_control(SimControl::StepOnce);
+        }
+        if is_key_pressed(KeyCode::Equal) {
+            shared.send_control(SimControl::SetSpeed(clock.speed * 2.0));
+        }
+        if is_key_pressed(KeyCode::Minus) {
+            shared.send_control(SimControl::SetSpeed(clock.speed / 2.0));
+        }
+        if is_key_pressed(KeyCode::Key0) {
+            shared.send_control(SimControl::SetSpeed(1.0));
+        }
+
+        // Brush and camera controls.
+        brush.handle_keys(&shared.mat_db);
+        camera.handle_input();
+
+        // Get current tick count.
+        let step = shared.tick_count.load(Ordering::Relaxed);
+
+        // Get latest snapshot from shared state.
+        let snapshot = shared.current.load();
+
+        // Draw world to render target.
+        clear_background(Color::from_rgba(10, 12, 16, 255));
+        for y in 0..snapshot.h {
+            for x in 0..snapshot.w {
+
+                if let Some(mat) = shared.mat_db.get(snapshot.mat_id_at(x, y)) {
+                    let mut mat_rgb = mat.color;
+
+                    if view_thermal {
+                        mat_rgb = thermal_view_color(mat_rgb, snapshot.temp_at(x, y), thermal_view_range);
+                    }
+                    img.set_pixel(x as u32, y as u32, to_mq_color(mat_rgb));
+                }
+            }
+        }
+
+        // Draw texture to screen
+        tex.update(&img);
+        set_default_camera();
+
+        let sw = screen_width();
+        let scale = camera.zoom;
+
+        draw_texture_ex(
+            &tex,
+            camera.offset.x,
+            camera.offset.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(camera.dest_size()),
+                ..Default::default()
+            },
+        );
+
+        // UI overlay
+        let tps = tps_tracker.update(&shared);
+        let total_time = get_time();
+
+        // Cell under the mouse, if any.
+        let hover_cell = camera.screen_to_cell(Vec2::from(mouse_position()));
+
+        // Material palette, then painting. Clicks on the palette do not paint the world.
+        let over_palette = brush.draw_palette(&shared.mat_db);
+        brush.handle_mouse(&shared, hover_cell.filter(|_| !over_palette));
+
+        // Mouse Tooltip and brush outline.
+        if let Some((grid_x, grid_y)) = hover_cell {
+            let temp = snapshot.temp_at(grid_x, grid_y);
+            draw_text(format!("Temp: {:.1}°C", temp), sw - 200.0, 24.0*1.0, 24.0, WHITE);
+            let mat_id = snapshot.mat_id_at(grid_x, grid_y);
+            if let Some(mat) = shared.mat_db.get(mat_id) {
+                draw_text(format!("Mat: {}", mat.name), sw - 200.0, 24.0*2.0, 24.0, WHITE);
+            }
+
+            if !over_palette {
+                let r = brush.radius as f32;
+                let center = camera.cell_to_screen(grid_x as f32 + 0.5, grid_y as f32 + 0.5);
+                let (cx, cy) = (center.x, center.y);
+                match brush.shape {
+                    brush::BrushShape::Circle => draw_circle_lines(cx, cy, (r + 0.5) * scale, 1.0, WHITE),
+                    brush::BrushShape::Square => {
+                        let side = (2.0 * r + 1.0) * scale;
+                        draw_rectangle_lines(cx - side * 0.5, cy - side * 0.5, side, side, 1.0, WHITE);
+                    }
+                }
+            }
+        }
+
+        // COL1
+        draw_text(format!("Sim Step: {}", step),                                                        10.0, 24.0*1.0, 24.0, BLUE);
+        draw_text(format!("TPS: {}", tps),                                                              10.0, 24.0*2.0, 24.0, SKYBLUE);
+        draw_text(format!("Real Secs: {}", total_time),                                                 10.0, 24.0*3.0, 24.0, SKYBLUE);
+
+        // World time runs at the target tick rate. It is undefined when the rate is unlimited.
+        let wtps = clock.target_tps;
+        let (sps, world_secs, world_hours) = if (wtps > 0.0) {
+            (format!("{}", tps / wtps), format!("{}", (step as f64 / wtps) as u64), format!("{}", step as f64 / 60.0 / 60.0 / wtps))
+        } else {
+            ("n/a".to_owned(), "n/a".to_owned(), "n/a".to_owned())
+        };
+        draw_text(format!("SPS: {}", sps),                                                              10.0, 24.0*4.0, 24.0, PURPLE);
+        draw_text(format!("World Secs: {}", world_secs),                                                10.0, 24.0*5.0, 24.0, PURPLE);
+        draw_text(format!("World Hours: {}", world_hours),                                              10.0, 24.0*6.0, 24.0, PURPLE);
+
+        let clock_text = if clock.paused { "PAUSED".to_owned() } else { format!("Speed: {}x", clock.speed) };
+        draw_text(clock_text,                                                                           10.0, 24.0*7.0, 24.0, ORANGE);
+        draw_text(brush.status_text(&shared.mat_db),                                                    10.0, 24.0*8.0, 24.0, YELLOW);
+
+        draw_text("Press [SPACE] to toggle Thermal View.",                                              screen_width()/2.0 - 140.0, 12.0, 20.0, WHITE);
+        draw_text("[P] pause  [.] step  [-/=] speed  [0] reset speed",                                  screen_width()/2.0 - 190.0, 32.0, 20.0, WHITE);
+        draw_text("[WHEEL] zoom  [MMB drag] pan  [R] reset view",                                     screen_width()/2.0 - 180.0, 72.0, 20.0, WHITE);
+        draw_text("[LMB] paint  [RMB] heat  [SHIFT+RMB] cool  [TAB] material  [E] eraser  [B] shape  [ [ ] ] size  [UP/DOWN] temp",
+                                                                                                        screen_width()/2.0 - 420.0, 52.0, 20.0, WHITE);
+
+        // COL2
+        // draw_text(format!("Tiles: {} x {}  ({})", w, h, w*h),                                           500.0, 24.0*1.0, 24.0, PURPLE);
+        // let meters_w = w as f32 / 2.0;
+        // let meters_h = h as f32 / 2.0;
+        // draw_text(format!("Meters: {} x {}  ({})", meters_w, meters_h, meters_w * meters_h),            500.0, 24.0*2.0, 24.0, PURPLE);
+        // let feet_w = meters_w * 3.28084;
+        // let feet_h = meters_h * 3.28084;
+        // draw_text(format!("Feet: {} x {}  ({})", feet_w, feet_h, feet_w * feet_h),                      500.0, 24.0*3.0, 24.0, PURPLE);
+
+
+        next_frame().await;
+    }
+}
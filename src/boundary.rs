@@ -0,0 +1,169 @@
+//! World boundary conditions: what lies past each edge of the world.
+
+use crate::material::{MaterialDb, MaterialId};
+use crate::physics::engine::empty_material_from_config;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// How one edge of the world treats heat and material crossing it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeMode {
+    /// No heat flows through the edge, and material treats it as a wall.
+    #[default]
+    Insulating,
+    /// Heat flows to and from a fixed ambient temperature. Material treats it as a wall.
+    Ambient,
+    /// Wraps around to the opposite edge, for both heat and material. The opposite edge
+    /// must also be periodic.
+    Periodic,
+    /// Material that crosses the edge is deleted, leaving the empty material behind. No
+    /// heat flows through the edge, and gas pressure vents to reference pressure.
+    Void,
+}
+
+/// What lies at an offset from a cell, according to the world's boundary.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Neighbor {
+    Cell(usize, usize),
+    /// Past an insulating or ambient edge.
+    Wall,
+    /// Past a void edge.
+    Void,
+}
+
+/// Edge modes as written in config, under the `boundary` key.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct BoundaryConfig {
+    north: EdgeMode,
+    south: EdgeMode,
+    west: EdgeMode,
+    east: EdgeMode,
+    ambient_temp: f32,
+}
+
+/// Boundary condition for each edge of the world. Modules look up neighbors through
+/// `CurrCtx::neighbor`, so every module treats the edges the same way.
+#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Boundary {
+    pub north: EdgeMode,
+    pub south: EdgeMode,
+    pub west: EdgeMode,
+    pub east: EdgeMode,
+    /// Temperature beyond ambient edges.
+    pub ambient_temp: f32,
+    /// Material left behind by cells deleted at a void edge. Config `empty_material`.
+    pub void_mat: MaterialId,
+}
+
+impl Boundary {
+    /// Read the `boundary` section of config. Every edge is insulating when it is missing.
+    pub fn from_config(config: &HashMap<String, Value>, mat_db: &MaterialDb) -> Result<Self> {
+        let raw = match config.get("boundary") {
+            Some(v) => BoundaryConfig::deserialize(v).context("Invalid config: boundary")?,
+            None => BoundaryConfig::default(),
+        };
+
+        if ((raw.west == EdgeMode::Periodic) != (raw.east == EdgeMode::Periodic)) {
+            bail!("Invalid config: boundary west and east edges must both be periodic, or neither");
+        }
+        if ((raw.north == EdgeMode::Periodic) != (raw.south == EdgeMode::Periodic)) {
+            bail!("Invalid config: boundary north and south edges must both be periodic, or neither");
+        }
+
+        // Empty material is only required by void edges.
+        let mut void_mat = MaterialId::default();
+        if [raw.north, raw.south, raw.west, raw.east].contains(&EdgeMode::Void) {
+            void_mat = empty_material_from_config(config, mat_db)?;
+        }
+
+        Ok(Self {
+            north: raw.north,
+            south: raw.south,
+            west: raw.west,
+            east: raw.east,
+            ambient_temp: raw.ambient_temp,
+            void_mat,
+        })
+    }
+
+    /// What lies at (x + dx, y + dy) in a `w` x `h` world, for offsets of at most one cell.
+    /// A diagonal past a corner is a wall if either edge is, then void if either edge is.
+    pub fn neighbor(&self, w: usize, h: usize, x: usize, y: usize, dx: isize, dy: isize) -> Neighbor {
+        let (nx, mode_x) = wrap_axis(x, dx, w, self.west, self.east);
+        let (ny, mode_y) = wrap_axis(y, dy, h, self.north, self.south);
+        match (mode_x, mode_y) {
+            (Some(EdgeMode::Insulating | EdgeMode::Ambient), _) | (_, Some(EdgeMode::Insulating | EdgeMode::Ambient)) => Neighbor::Wall,
+            (Some(EdgeMode::Void), _) | (_, Some(EdgeMode::Void)) => Neighbor::Void,
+            _ => Neighbor::Cell(nx, ny),
+        }
+    }
+}
+
+/// Step `v` by `d` along an axis of length `len`. Returns the new coordinate, wrapped if the
+/// crossed edge is periodic, and the mode of the edge crossed, if any.
+#[inline]
+fn wrap_axis(v: usize, d: isize, len: usize, low: EdgeMode, high: EdgeMode) -> (usize, Option<EdgeMode>) {
+    let n = v as isize + d;
+    if (n < 0) {
+        (len - 1, (low != EdgeMode::Periodic).then_some(low))
+    } else if (n as usize >= len) {
+        (0, (high != EdgeMode::Periodic).then_some(high))
+    } else {
+        (n as usize, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mat_db() -> MaterialDb {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"{ "base:air": (), "test:rock": () }"#).unwrap();
+        mat_db
+    }
+
+    fn config(boundary: &str) -> HashMap<String, Value> {
+        ron::de::from_str(&format!("{{ \"boundary\": {} }}", boundary)).unwrap()
+    }
+
+    #[test]
+    fn test_boundary_from_config() {
+        let mat_db = mat_db();
+
+        // Missing section is insulating everywhere.
+        let boundary = Boundary::from_config(&HashMap::new(), &mat_db).unwrap();
+        assert_eq!(boundary, Boundary::default());
+
+        let boundary = Boundary::from_config(&config(r#"{ "west": "periodic", "east": "periodic", "south": "void", "north": "ambient", "ambient_temp": 15.0 }"#), &mat_db).unwrap();
+        assert_eq!(boundary.west, EdgeMode::Periodic);
+        assert_eq!(boundary.south, EdgeMode::Void);
+        assert_eq!(boundary.north, EdgeMode::Ambient);
+        assert_eq!(boundary.ambient_temp, 15.0);
+        assert_eq!(boundary.void_mat, mat_db.get_id("base:air").unwrap());
+
+        // Periodic edges come in pairs.
+        assert!(Boundary::from_config(&config(r#"{ "west": "periodic" }"#), &mat_db).is_err());
+        assert!(Boundary::from_config(&config(r#"{ "north": "sideways" }"#), &mat_db).is_err());
+    }
+
+    #[test]
+    fn test_boundary_neighbor() {
+        let boundary = Boundary { west: EdgeMode::Periodic, east: EdgeMode::Periodic, south: EdgeMode::Void, ..Boundary::default() };
+
+        // Inside the world, and wrapping through periodic edges.
+        assert_eq!(boundary.neighbor(4, 3, 1, 1, 1, 0), Neighbor::Cell(2, 1));
+        assert_eq!(boundary.neighbor(4, 3, 3, 1, 1, -1), Neighbor::Cell(0, 0));
+        assert_eq!(boundary.neighbor(4, 3, 0, 1, -1, 0), Neighbor::Cell(3, 1));
+
+        // Insulating north is a wall, void south deletes, and a wall wins at a corner.
+        assert_eq!(boundary.neighbor(4, 3, 1, 0, 0, -1), Neighbor::Wall);
+        assert_eq!(boundary.neighbor(4, 3, 0, 2, -1, 1), Neighbor::Void);
+        let boundary = Boundary { west: EdgeMode::Insulating, ..boundary };
+        assert_eq!(boundary.neighbor(4, 3, 0, 2, -1, 1), Neighbor::Wall);
+    }
+}
@@ -0,0 +1,222 @@
+//! Fixed-rate tick scheduling for the Sim thread.
+
+use std::time::Duration;
+
+/// Ticks per second the sim runs at when config does not say otherwise.
+pub const DEFAULT_TARGET_TPS: f64 = 20.0;
+
+/// Never schedule more than this much real time worth of ticks at once. If the engine falls
+/// behind (e.g. a slow tick or a debugger pause), the backlog is dropped instead of the sim
+/// spiraling into an ever-growing catch-up.
+const MAX_CATCH_UP_SECS: f64 = 0.25;
+
+/// Speed multiplier limits for fast-forward and slow motion.
+pub const MIN_SPEED: f64 = 0.125;
+pub const MAX_SPEED: f64 = 256.0;
+
+/// Commands sent from the Render thread (or game logic) to control the Sim thread clock.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SimControl {
+    Pause,
+    Resume,
+    TogglePause,
+    /// Run exactly one tick. Only has an effect while paused.
+    StepOnce,
+    /// Base tick rate. Zero or less runs as fast as possible. Ignored if not finite.
+    SetTargetTps(f64),
+    /// Multiplier on the target tick rate, used for fast-forward and slow motion. Ignored if
+    /// not finite.
+    SetSpeed(f64),
+}
+
+/// Clock state that the Render thread can display.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClockStatus {
+    pub paused: bool,
+    pub target_tps: f64,
+    pub speed: f64,
+}
+
+/// Decides how many ticks the Sim thread should run, given how much real time has passed.
+/// Uses a fixed-timestep accumulator, so the average tick rate stays exact even though
+/// individual loop iterations have uneven durations.
+#[derive(Clone, Debug)]
+pub struct SimClock {
+    paused: bool,
+    target_tps: f64,
+    speed: f64,
+
+    /// Fractional ticks owed from previous calls.
+    accumulator: f64,
+    /// Single steps requested while paused.
+    pending_steps: u64,
+}
+
+impl SimClock {
+    pub fn new(target_tps: f64) -> Self {
+        Self {
+            paused: false,
+            target_tps,
+            speed: 1.0,
+            accumulator: 0.0,
+            pending_steps: 0,
+        }
+    }
+
+    pub fn status(&self) -> ClockStatus {
+        ClockStatus {
+            paused: self.paused,
+            target_tps: self.target_tps,
+            speed: self.speed,
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.target_tps <= 0.0
+    }
+
+    pub fn apply(&mut self, cmd: SimControl) {
+        match cmd {
+            SimControl::Pause => self.set_paused(true),
+            SimControl::Resume => self.set_paused(false),
+            SimControl::TogglePause => self.set_paused(!self.paused),
+            SimControl::StepOnce => {
+                if self.paused {
+                    self.pending_steps += 1;
+                }
+            }
+            SimControl::SetTargetTps(tps) => {
+                if tps.is_finite() {
+                    self.target_tps = tps.max(0.0);
+                    self.accumulator = 0.0;
+                }
+            }
+            SimControl::SetSpeed(speed) => {
+                if speed.is_finite() {
+                    self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+                }
+            }
+        }
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.accumulator = 0.0;
+        self.pending_steps = 0;
+    }
+
+    /// Ticks per second after applying the speed multiplier.
+    fn effective_tps(&self) -> f64 {
+        self.target_tps * self.speed
+    }
+
+    /// Advance the clock by `elapsed` real time and return the number of ticks to run now.
+    pub fn ticks_due(&mut self, elapsed: Duration) -> u64 {
+        if self.paused {
+            return std::mem::take(&mut self.pending_steps);
+        }
+        if self.is_unlimited() {
+            return 1;
+        }
+
+        let tps = self.effective_tps();
+        let max_ticks = (tps * MAX_CATCH_UP_SECS).ceil().max(1.0);
+        self.accumulator = (self.accumulator + elapsed.as_secs_f64() * tps).min(max_ticks);
+
+        let due = self.accumulator.floor();
+        self.accumulator -= due;
+        due as u64
+    }
+
+    /// How long the Sim thread can sleep before the next tick is due. `None` means it should
+    /// wait for a command (paused) and `Duration::ZERO` means it should not sleep at all.
+    pub fn time_until_next_tick(&self) -> Option<Duration> {
+        if self.paused {
+            return if self.pending_steps > 0 { Some(Duration::ZERO) } else { None };
+        }
+        if self.is_unlimited() {
+            return Some(Duration::ZERO);
+        }
+        let remaining = (1.0 - self.accumulator).max(0.0) / self.effective_tps();
+        Some(Duration::from_secs_f64(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: f64) -> Duration {
+        Duration::from_secs_f64(s)
+    }
+
+    #[test]
+    fn test_clock_fixed_rate() {
+        let mut clock = SimClock::new(20.0);
+        assert_eq!(clock.ticks_due(secs(0.1)), 2);
+
+        // Fractional ticks accumulate across calls.
+        assert_eq!(clock.ticks_due(secs(0.025)), 0);
+        assert_eq!(clock.ticks_due(secs(0.025)), 1);
+    }
+
+    #[test]
+    fn test_clock_catch_up_is_capped() {
+        let mut clock = SimClock::new(20.0);
+
+        // A long stall does not produce a huge burst of ticks.
+        assert_eq!(clock.ticks_due(secs(10.0)), 5);
+        assert_eq!(clock.ticks_due(secs(0.0)), 0);
+    }
+
+    #[test]
+    fn test_clock_pause_and_step() {
+        let mut clock = SimClock::new(20.0);
+        clock.apply(SimControl::Pause);
+        assert_eq!(clock.ticks_due(secs(1.0)), 0);
+        assert_eq!(clock.time_until_next_tick(), None);
+
+        // Each step request runs exactly one tick.
+        clock.apply(SimControl::StepOnce);
+        clock.apply(SimControl::StepOnce);
+        assert_eq!(clock.time_until_next_tick(), Some(Duration::ZERO));
+        assert_eq!(clock.ticks_due(secs(0.0)), 2);
+        assert_eq!(clock.ticks_due(secs(1.0)), 0);
+
+        // Stepping is ignored while running.
+        clock.apply(SimControl::Resume);
+        clock.apply(SimControl::StepOnce);
+        assert_eq!(clock.ticks_due(secs(0.0)), 0);
+        assert!(!clock.status().paused);
+    }
+
+    #[test]
+    fn test_clock_speed() {
+        let mut clock = SimClock::new(20.0);
+        clock.apply(SimControl::SetSpeed(2.0));
+        assert_eq!(clock.ticks_due(secs(0.1)), 4);
+
+        clock.apply(SimControl::SetSpeed(1_000_000.0));
+        assert_eq!(clock.status().speed, MAX_SPEED);
+    }
+
+    #[test]
+    fn test_clock_ignores_non_finite() {
+        let mut clock = SimClock::new(20.0);
+        clock.apply(SimControl::SetSpeed(f64::NAN));
+        clock.apply(SimControl::SetSpeed(f64::INFINITY));
+        clock.apply(SimControl::SetTargetTps(f64::NAN));
+        clock.apply(SimControl::SetTargetTps(f64::INFINITY));
+        assert_eq!(clock.status(), ClockStatus { paused: false, target_tps: 20.0, speed: 1.0 });
+        assert_eq!(clock.ticks_due(secs(0.1)), 2);
+        assert!(clock.time_until_next_tick().is_some());
+    }
+
+    #[test]
+    fn test_clock_unlimited() {
+        let mut clock = SimClock::new(0.0);
+        assert!(clock.is_unlimited());
+        assert_eq!(clock.ticks_due(secs(0.0)), 1);
+        assert_eq!(clock.time_until_next_tick(), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,95 @@
+use std::hash::Hasher;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x00000100000001b3;
+
+/// 64-bit FNV-1a hasher. Unlike `DefaultHasher`, the output is stable across processes,
+/// platforms and Rust versions, so it is safe to compare hashes between two machines.
+/// Integers are always fed in little-endian order, and `usize` and `isize` are widened to
+/// 64 bits.
+#[derive(Copy, Clone, Debug)]
+pub struct StableHasher {
+    state: u64,
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self { state: FNV_OFFSET_BASIS }
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.state ^= b as u64;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) { self.write(&i.to_le_bytes()); }
+    fn write_u32(&mut self, i: u32) { self.write(&i.to_le_bytes()); }
+    fn write_u64(&mut self, i: u64) { self.write(&i.to_le_bytes()); }
+    fn write_u128(&mut self, i: u128) { self.write(&i.to_le_bytes()); }
+    fn write_usize(&mut self, i: usize) { self.write_u64(i as u64); }
+    fn write_i16(&mut self, i: i16) { self.write_u16(i as u16); }
+    fn write_i32(&mut self, i: i32) { self.write_u32(i as u32); }
+    fn write_i64(&mut self, i: i64) { self.write_u64(i as u64); }
+    fn write_i128(&mut self, i: i128) { self.write_u128(i as u128); }
+    fn write_isize(&mut self, i: isize) { self.write_u64(i as i64 as u64); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::Hash;
+
+    #[test]
+    fn test_fnv1a_known_values() {
+        // Reference values for 64-bit FNV-1a.
+        let mut h = StableHasher::new();
+        h.write(b"");
+        assert_eq!(h.finish(), 0xcbf29ce484222325);
+
+        let mut h = StableHasher::new();
+        h.write(b"a");
+        assert_eq!(h.finish(), 0xaf63dc4c8601ec8c);
+
+        let mut h = StableHasher::new();
+        h.write(b"foobar");
+        assert_eq!(h.finish(), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_usize_hashes_as_u64() {
+        let mut a = StableHasher::new();
+        7usize.hash(&mut a);
+        let mut b = StableHasher::new();
+        7u64.hash(&mut b);
+        assert_eq!(a.finish(), b.finish());
+    }
+
+    #[test]
+    fn test_integers_hash_little_endian() {
+        let mut a = StableHasher::new();
+        0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10u128.hash(&mut a);
+        let mut b = StableHasher::new();
+        b.write(&[0x10, 0x0f, 0x0e, 0x0d, 0x0c, 0x0b, 0x0a, 0x09, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(a.finish(), b.finish());
+
+        let mut a = StableHasher::new();
+        (-2isize).hash(&mut a);
+        let mut b = StableHasher::new();
+        (-2i64).hash(&mut b);
+        assert_eq!(a.finish(), b.finish());
+    }
+}
@@ -0,0 +1,16 @@
+//! Mintage is a multithreaded 2D cellular physics engine.
+//!
+//! The library contains the simulation itself (world state, materials, reactions and
+//! physics modules) and has no dependency on any windowing or rendering stack. The
+//! demo/visualizer lives in its own binary behind the `visualizer` feature.
+
+pub mod boundary;
+pub mod clock;
+pub mod hash;
+pub mod material;
+pub mod physics;
+pub mod reaction;
+pub mod render;
+pub mod save;
+pub mod sim;
+pub mod world;
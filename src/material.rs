@@ -1,14 +1,80 @@
 ﻿use std::collections::HashMap;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use anyhow::Result;
-use macroquad::color::Color;
 use ron::de::from_str;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use crate::hash::StableHasher;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub struct MaterialId(pub u16);
 
-#[derive(Deserialize, Default, Clone, Debug)]
+/// RGBA color with components in `0.0..=1.0`. Kept independent of any rendering
+/// backend so the simulation can be used without a windowing stack.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
+    }
+}
+
+/// Physical phase of a material. Movement modules use it to decide how (and whether) a cell moves.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// Never moves.
+    #[default]
+    Solid,
+    /// Falls, and slides diagonally off piles.
+    Powder,
+    /// Falls, and spreads horizontally.
+    Liquid,
+    /// Moved by gas modules, not by gravity.
+    Gas,
+}
+
+/// A spontaneous single-cell transform, such as ash crumbling away or fire burning out.
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct DecayRule {
+    #[serde(skip)]
+    pub out_id: MaterialId,
+    pub out_name: String,
+    /// Chance per tick that the cell decays, once it is old enough.
+    pub chance: f32,
+    /// Ticks since the cell became this material before it can decay.
+    pub min_age: u32,
+}
+
+/// Holds a cell near a fixed temperature, such as a forge, a cryo vent or the sun.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Thermostat {
+    /// Temperature the cell is driven toward.
+    pub temp: f32,
+    /// Fraction of the gap to `temp` closed each tick, in `0.0..=1.0`. At 1.0 the cell is
+    /// set back to `temp` every tick, plus whatever heat other modules move that tick.
+    pub strength: f32,
+}
+
+/// Smallest heat capacity a material can have, so heat flow always gives a finite
+/// temperature change.
+pub const MIN_HEAT_CAPACITY: f32 = 1e-3;
+
+fn default_heat_capacity() -> f32 { 1.0 }
+fn default_opacity() -> f32 { 1.0 }
+
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
 #[serde(default)]
 pub struct Material {
     #[serde(skip)]
@@ -16,25 +82,107 @@ pub struct Material {
     #[serde(skip)]
     pub color: Color,
     pub color_raw: (u8, u8, u8, u8),
+    /// Heat conducted per tick per degree of difference across each edge. The explicit
+    /// thermal solver caps edges at 0.25 times the lower heat capacity of their cells, its
+    /// stability limit; the implicit one does not.
     pub diffusivity: f32,
+    /// Heat needed to warm one cell by one degree, relative to other materials. A cell with
+    /// twice the heat capacity changes temperature half as much for the same heat flow.
+    /// Clamped to at least `MIN_HEAT_CAPACITY`.
+    #[serde(default = "default_heat_capacity")]
+    pub heat_capacity: f32,
+    pub phase: Phase,
+    /// Relative density. Denser powders and liquids sink through lighter non-solids. For
+    /// gases this is the density at 20°C; hotter gas is proportionally lighter.
+    pub density: f32,
+
+    /// Thermostatic source. Cells of this material are driven toward `thermostat_temp` by
+    /// `thermostat_strength` each tick. Zero strength (the default) is not a source.
+    pub thermostat_temp: f32,
+    pub thermostat_strength: f32,
+
+    /// Chance per tick that a gas cell turns into its fade product (e.g. smoke into air).
+    pub fade_chance: f32,
+    #[serde(skip)]
+    pub fade_product_id: Option<MaterialId>,
+    pub fade_product_name: String,
+
+    /// Decay rules, tried in order each tick. The first that rolls its chance applies.
+    pub decay: Vec<DecayRule>,
+
+    /// Combustion. A material with `burn_ticks` above zero is flammable: once it reaches
+    /// `ignition_temp` it becomes fire, which burns for `burn_ticks`, releasing `burn_heat`
+    /// degrees into the fire cell each tick and puffing `burn_smoke` into neighboring gas
+    /// with `burn_smoke_chance` per tick, then leaves `burn_product` behind.
+    pub ignition_temp: f32,
+    pub burn_ticks: u32,
+    pub burn_heat: f32,
+    #[serde(skip)]
+    pub burn_product_id: Option<MaterialId>,
+    pub burn_product_name: String,
+    #[serde(skip)]
+    pub burn_smoke_id: Option<MaterialId>,
+    pub burn_smoke_name: String,
+    pub burn_smoke_chance: f32,
+
+    /// Blast power a cell withstands. Cells hit with more power than this are blown apart.
+    pub hardness: f32,
+
+    /// Thermal radiation. Hot cells radiate `emissivity` of a black body's heat, in
+    /// `0.0..=1.0`. Radiation crossing a cell loses `opacity` of what is left to it, so
+    /// transparent materials such as air have 0.0, and opaque ones (the default) 1.0.
+    pub emissivity: f32,
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+
+    /// Explosives. A material with `blast_radius` above zero detonates once it reaches
+    /// `blast_temp`, blowing apart cells within the radius that are softer than the blast
+    /// power there, and heating everything within the radius by up to `blast_heat`. Power and
+    /// heat fall off linearly with distance from the center.
+    pub blast_temp: f32,
+    pub blast_radius: u16,
+    pub blast_power: f32,
+    pub blast_heat: f32,
 
     #[serde(skip)]
     pub transform_cold_mat_id: Option<MaterialId>,
     pub transform_cold_mat_name: String,
     pub transform_cold_temp: f32,
+    /// Heat released by the cold transform, in degrees of this material. The cell stays at
+    /// `transform_cold_temp` while it gives this much heat away, then transforms.
+    pub transform_cold_latent_heat: f32,
 
     #[serde(skip)]
     pub transform_hot_mat_id: Option<MaterialId>,
     pub transform_hot_mat_name: String,
     pub transform_hot_temp: f32,
+    /// Heat absorbed by the hot transform, in degrees of this material. The cell stays at
+    /// `transform_hot_temp` while it takes this much heat in, then transforms.
+    pub transform_hot_latent_heat: f32,
 }
 
+#[derive(Default)]
 pub struct MaterialDb {
     defs: Vec<Material>,
     by_name: HashMap<String, MaterialId>,
 
-    /// Diffusivity indexed by material ID, packed for cache locality during conductance updates.
+    /// Diffusivity and heat capacity indexed by material ID, packed for cache locality during diffusion.
     diffusivity_lookup: Box<[f32]>,
+    heat_capacity_lookup: Box<[f32]>,
+
+    /// Phase and density indexed by material ID, for movement modules.
+    phase_lookup: Box<[Phase]>,
+    density_lookup: Box<[f32]>,
+
+    /// Thermostat indexed by material ID. None for materials that are not sources.
+    thermostat_lookup: Box<[Option<Thermostat>]>,
+
+    /// Hardness indexed by material ID, for resolving blasts.
+    hardness_lookup: Box<[f32]>,
+
+    /// Emissivity and opacity indexed by material ID, for radiation.
+    emissivity_lookup: Box<[f32]>,
+    opacity_lookup: Box<[f32]>,
 }
 
 impl MaterialDb {
@@ -43,6 +191,13 @@ impl MaterialDb {
             defs: vec![],
             by_name: HashMap::new(),
             diffusivity_lookup: Box::default(),
+            heat_capacity_lookup: Box::default(),
+            phase_lookup: Box::default(),
+            density_lookup: Box::default(),
+            thermostat_lookup: Box::default(),
+            hardness_lookup: Box::default(),
+            emissivity_lookup: Box::default(),
+            opacity_lookup: Box::default(),
         }
     }
 
@@ -65,6 +220,21 @@ impl MaterialDb {
 
     pub fn get_mat_count(&self) -> usize { self.defs.len() }
 
+    /// Stable hash of every material definition, in ID order. Processes that loaded the
+    /// same data produce the same value, so save files, replays and networked peers can
+    /// use it to verify they agree on the database.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.defs.len().hash(&mut hasher);
+        for mat in &self.defs {
+            // Name is skipped by serde, so hash it separately. Serializing the rest covers
+            // every data-driven field without having to list them here.
+            mat.name.hash(&mut hasher);
+            ron::to_string(mat).expect("material must serialize").hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
     #[inline(always)]
     pub fn get_diffusivity_lookup(&self) -> &[f32] {
         &self.diffusivity_lookup
@@ -75,36 +245,112 @@ impl MaterialDb {
         self.diffusivity_lookup[id.0 as usize]
     }
 
+    #[inline(always)]
+    pub fn get_heat_capacity_lookup(&self) -> &[f32] {
+        &self.heat_capacity_lookup
+    }
+
+    #[inline(always)]
+    pub fn heat_capacity_of(&self, id: MaterialId) -> f32 {
+        self.heat_capacity_lookup[id.0 as usize]
+    }
+
+    #[inline(always)]
+    pub fn get_phase_lookup(&self) -> &[Phase] {
+        &self.phase_lookup
+    }
+
+    #[inline(always)]
+    pub fn phase_of(&self, id: MaterialId) -> Phase {
+        self.phase_lookup[id.0 as usize]
+    }
+
+    #[inline(always)]
+    pub fn density_of(&self, id: MaterialId) -> f32 {
+        self.density_lookup[id.0 as usize]
+    }
+
+    #[inline(always)]
+    pub fn thermostat_of(&self, id: MaterialId) -> Option<Thermostat> {
+        self.thermostat_lookup[id.0 as usize]
+    }
+
+    #[inline(always)]
+    pub fn hardness_of(&self, id: MaterialId) -> f32 {
+        self.hardness_lookup[id.0 as usize]
+    }
+
+    #[inline(always)]
+    pub fn get_emissivity_lookup(&self) -> &[f32] {
+        &self.emissivity_lookup
+    }
+
+    #[inline(always)]
+    pub fn get_opacity_lookup(&self) -> &[f32] {
+        &self.opacity_lookup
+    }
+
+    #[inline(always)]
+    pub fn opacity_of(&self, id: MaterialId) -> f32 {
+        self.opacity_lookup[id.0 as usize]
+    }
+
     pub fn load_ron_file(&mut self, path: &str) -> Result<()> {
         let text = fs::read_to_string(path)?;
         self.load_ron_str(&text)
     }
 
     pub fn load_ron_str(&mut self, text: &str) -> Result<()> {
-        let mut map: HashMap<String, Material> = from_str(text)?;
+        let map: HashMap<String, Material> = from_str(text)?;
+
+        // Sort by name so IDs are assigned in a stable order. HashMap iteration order is
+        // randomized per process, which would give materials different IDs on every run.
+        let mut entries: Vec<(String, Material)> = map.into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
 
         // Build defs from loaded string.
-        for (name, mut mat) in map.drain() {
+        for (name, mut mat) in entries {
             mat.name = name.clone(); // Populate the skipped field.
             mat.color = Color::from_rgba(mat.color_raw.0, mat.color_raw.1, mat.color_raw.2, mat.color_raw.3);
-            mat.diffusivity = mat.diffusivity.clamp(0.0, 0.25);
+            mat.diffusivity = mat.diffusivity.max(0.0);
+            mat.heat_capacity = mat.heat_capacity.max(MIN_HEAT_CAPACITY);
+            mat.thermostat_strength = mat.thermostat_strength.clamp(0.0, 1.0);
+            mat.fade_chance = mat.fade_chance.clamp(0.0, 1.0);
+            mat.burn_smoke_chance = mat.burn_smoke_chance.clamp(0.0, 1.0);
+            mat.emissivity = mat.emissivity.clamp(0.0, 1.0);
+            mat.opacity = mat.opacity.clamp(0.0, 1.0);
+            for rule in &mut mat.decay {
+                rule.chance = rule.chance.clamp(0.0, 1.0);
+            }
             self.insert(mat);
         }
 
-        // Build diffusivity lookup.
+        // Build lookups.
         self.diffusivity_lookup = self.defs.iter().map(|m| m.diffusivity).collect::<Box<[f32]>>();
+        self.heat_capacity_lookup = self.defs.iter().map(|m| m.heat_capacity).collect::<Box<[f32]>>();
+        self.phase_lookup = self.defs.iter().map(|m| m.phase).collect::<Box<[Phase]>>();
+        self.density_lookup = self.defs.iter().map(|m| m.density).collect::<Box<[f32]>>();
+        self.thermostat_lookup = self.defs.iter()
+            .map(|m| (m.thermostat_strength > 0.0).then_some(Thermostat { temp: m.thermostat_temp, strength: m.thermostat_strength }))
+            .collect::<Box<[Option<Thermostat>]>>();
+        self.hardness_lookup = self.defs.iter().map(|m| m.hardness).collect::<Box<[f32]>>();
+        self.emissivity_lookup = self.defs.iter().map(|m| m.emissivity).collect::<Box<[f32]>>();
+        self.opacity_lookup = self.defs.iter().map(|m| m.opacity).collect::<Box<[f32]>>();
 
         // Get material IDs for transforms.
-        let ids: Vec<(Option<MaterialId>, Option<MaterialId>)> = self.defs.iter()
+        let ids: Vec<_> = self.defs.iter()
             .map(|m| {
                 let cold = self.get_id(&m.transform_cold_mat_name);
                 let hot  = self.get_id(&m.transform_hot_mat_name);
-                (cold, hot)
+                let fade = self.get_id(&m.fade_product_name);
+                let decay: Vec<Option<MaterialId>> = m.decay.iter().map(|rule| self.get_id(&rule.out_name)).collect();
+                let burn = (self.get_id(&m.burn_product_name), self.get_id(&m.burn_smoke_name));
+                (cold, hot, fade, decay, burn)
             })
             .collect();
 
         // Assign material IDs for transforms. (Two passes due to borrow checker.)
-        for (mat, (cold, hot)) in self.defs.iter_mut().zip(ids) {
+        for (mat, (cold, hot, fade, decay, (burn_product, burn_smoke))) in self.defs.iter_mut().zip(ids) {
 
             // Panic if transform reference is invalid (non-empty name but material not found).
             if !mat.transform_cold_mat_name.is_empty() && cold.is_none() {
@@ -122,8 +368,49 @@ impl MaterialDb {
                        mat.name, mat.transform_hot_temp, mat.transform_cold_temp);
             }
 
+            // Panic if latent heat is negative.
+            if (mat.transform_cold_latent_heat < 0.0 || mat.transform_hot_latent_heat < 0.0) {
+                panic!("Invalid material configuration: Material '{}' has negative latent heat", mat.name);
+            }
+
+            // Panic if fade product is invalid, or missing while the material can fade.
+            if !mat.fade_product_name.is_empty() && fade.is_none() {
+                panic!("Invalid material reference: Material '{}' references missing fade product '{}'",
+                       mat.name, mat.fade_product_name);
+            }
+            if mat.fade_chance > 0.0 && fade.is_none() {
+                panic!("Invalid material configuration: Material '{}' has a fade chance but no fade product",
+                       mat.name);
+            }
+
+            // Panic if any decay product is invalid.
+            for (rule, out) in mat.decay.iter_mut().zip(decay) {
+                match out {
+                    Some(out) => rule.out_id = out,
+                    None => panic!("Invalid material reference: Material '{}' references missing decay product '{}'",
+                                   mat.name, rule.out_name),
+                }
+            }
+
             mat.transform_cold_mat_id = cold;
             mat.transform_hot_mat_id  = hot;
+            mat.fade_product_id       = fade;
+
+            // Panic if combustion products are invalid, or a flammable material has nothing to burn into.
+            if !mat.burn_product_name.is_empty() && burn_product.is_none() {
+                panic!("Invalid material reference: Material '{}' references missing burn product '{}'",
+                       mat.name, mat.burn_product_name);
+            }
+            if !mat.burn_smoke_name.is_empty() && burn_smoke.is_none() {
+                panic!("Invalid material reference: Material '{}' references missing burn smoke '{}'",
+                       mat.name, mat.burn_smoke_name);
+            }
+            if mat.burn_ticks > 0 && burn_product.is_none() {
+                panic!("Invalid material configuration: Material '{}' is flammable but has no burn product",
+                       mat.name);
+            }
+            mat.burn_product_id = burn_product;
+            mat.burn_smoke_id   = burn_smoke;
         }
 
         Ok(())
@@ -146,12 +433,12 @@ mod tests {
             let mat_diamond = mat_db.get(mat_id_diamond).unwrap();
             let diamond_diff = mat_diamond.diffusivity;
 
-            // Ensure diffusivity is clamped. In test file, Diamond is set to 1.0, which must
-            // be clamped to 0.25 to prevent terrible oscillations and other bugs.
-            assert_eq!(diamond_diff, 0.25);
+            // Ensure diffusivity is not capped. In test file, Diamond is set to 1.0, above the
+            // explicit solver's stability limit, which the solver enforces itself.
+            assert_eq!(diamond_diff, 1.0);
 
-            // Ensure diffusivity lookup has same clamped value.
-            assert_eq!(mat_db.diffusivity_of(mat_id_diamond), 0.25);
+            // Ensure diffusivity lookup has same value.
+            assert_eq!(mat_db.diffusivity_of(mat_id_diamond), 1.0);
         }
 
         {
@@ -207,6 +494,183 @@ mod tests {
         mat_db.load_ron_file("assets_test/materials_test_invalid_transform_temps.ron").unwrap();
     }
 
+    #[test]
+    fn test_ids_assigned_in_name_order() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+
+        // IDs must not depend on HashMap iteration order.
+        let names: Vec<&str> = (0..mat_db.get_mat_count())
+            .map(|i| mat_db.get(MaterialId(i as u16)).unwrap().name.as_str())
+            .collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+        assert_eq!(mat_db.get_id("base:air"), Some(MaterialId(0)));
+    }
+
+    #[test]
+    fn test_content_hash_stable() {
+        let mut mat_db_a = MaterialDb::new();
+        mat_db_a.load_ron_file("assets_test/materials_test.ron").unwrap();
+        let mut mat_db_b = MaterialDb::new();
+        mat_db_b.load_ron_file("assets_test/materials_test.ron").unwrap();
+
+        // Same data, same hash.
+        assert_eq!(mat_db_a.content_hash(), mat_db_b.content_hash());
+
+        // Any change in data changes the hash.
+        let mut mat_db_c = MaterialDb::new();
+        mat_db_c.load_ron_str(r#"{ "base:air": ( diffusivity: 0.05 ) }"#).unwrap();
+        let mut mat_db_d = MaterialDb::new();
+        mat_db_d.load_ron_str(r#"{ "base:air": ( diffusivity: 0.06 ) }"#).unwrap();
+        assert_ne!(mat_db_c.content_hash(), mat_db_d.content_hash());
+    }
+
+    #[test]
+    fn test_phase_and_density() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "test:rock": (),
+                "test:sand": ( phase: powder, density: 1600.0 ),
+                "test:water": ( phase: liquid, density: 1000.0 ),
+            }
+        "#).unwrap();
+
+        let rock = mat_db.get_id("test:rock").unwrap();
+        let sand = mat_db.get_id("test:sand").unwrap();
+        let water = mat_db.get_id("test:water").unwrap();
+
+        // Phase defaults to solid.
+        assert_eq!(mat_db.phase_of(rock), Phase::Solid);
+        assert_eq!(mat_db.phase_of(sand), Phase::Powder);
+        assert_eq!(mat_db.phase_of(water), Phase::Liquid);
+        assert_eq!(mat_db.density_of(sand), 1600.0);
+    }
+
+    #[test]
+    fn test_heat_capacity() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "test:rock": (),
+                "test:water": ( heat_capacity: 4.0 ),
+                "test:air": ( heat_capacity: 0.25 ),
+                "test:vacuum": ( heat_capacity: 0.0 ),
+            }
+        "#).unwrap();
+
+        // Heat capacity defaults to one, may be lower, and is clamped to stay positive.
+        assert_eq!(mat_db.heat_capacity_of(mat_db.get_id("test:rock").unwrap()), 1.0);
+        assert_eq!(mat_db.heat_capacity_of(mat_db.get_id("test:water").unwrap()), 4.0);
+        assert_eq!(mat_db.heat_capacity_of(mat_db.get_id("test:air").unwrap()), 0.25);
+        assert_eq!(mat_db.heat_capacity_of(mat_db.get_id("test:vacuum").unwrap()), MIN_HEAT_CAPACITY);
+    }
+
+    #[test]
+    fn test_thermostat() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "test:rock": ( thermostat_temp: 500.0 ),
+                "test:forge": ( thermostat_temp: 1200.0, thermostat_strength: 4.0 ),
+            }
+        "#).unwrap();
+
+        // A material is only a source with a positive strength, which is clamped to one.
+        assert_eq!(mat_db.thermostat_of(mat_db.get_id("test:rock").unwrap()), None);
+        assert_eq!(mat_db.thermostat_of(mat_db.get_id("test:forge").unwrap()), Some(Thermostat { temp: 1200.0, strength: 1.0 }));
+    }
+
+    #[test]
+    fn test_combustion_and_blast_fields() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "test:ash": (),
+                "test:smoke": ( phase: gas ),
+                "test:wood": ( ignition_temp: 250.0, burn_ticks: 40, burn_product_name: "test:ash", burn_smoke_name: "test:smoke", burn_smoke_chance: 3.0 ),
+                "test:stone": ( hardness: 5.0 ),
+            }
+        "#).unwrap();
+
+        let wood = mat_db.get(mat_db.get_id("test:wood").unwrap()).unwrap();
+        assert_eq!(wood.burn_product_id, mat_db.get_id("test:ash"));
+        assert_eq!(wood.burn_smoke_id, mat_db.get_id("test:smoke"));
+        assert_eq!(wood.burn_smoke_chance, 1.0); // Clamped.
+        assert_eq!(mat_db.hardness_of(mat_db.get_id("test:stone").unwrap()), 5.0);
+    }
+
+    #[test]
+    fn test_radiation_fields() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "test:air": ( phase: gas, opacity: 0.0 ),
+                "test:rock": (),
+                "test:lava": ( emissivity: 2.0, opacity: -1.0 ),
+            }
+        "#).unwrap();
+
+        // Materials are opaque unless they say otherwise, and both fields are clamped.
+        assert_eq!(mat_db.opacity_of(mat_db.get_id("test:air").unwrap()), 0.0);
+        assert_eq!(mat_db.opacity_of(mat_db.get_id("test:rock").unwrap()), 1.0);
+        let lava = mat_db.get_id("test:lava").unwrap();
+        assert_eq!(mat_db.get_emissivity_lookup()[lava.0 as usize], 1.0);
+        assert_eq!(mat_db.opacity_of(lava), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid material configuration")]
+    fn test_flammable_requires_burn_product() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"{ "test:wood": ( ignition_temp: 250.0, burn_ticks: 40 ) }"#).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid material configuration")]
+    fn test_fade_chance_requires_product() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"{ "test:smoke": ( phase: gas, fade_chance: 0.1 ) }"#).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid material configuration")]
+    fn test_negative_latent_heat() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "test:ice": ( transform_hot_mat_name: "test:water", transform_hot_latent_heat: -80.0 ),
+                "test:water": (),
+            }
+        "#).unwrap();
+    }
+
+    #[test]
+    fn test_decay_rules() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "test:air": (),
+                "test:ash": ( decay: [ ( out_name: "test:air", chance: 2.0, min_age: 100 ) ] ),
+            }
+        "#).unwrap();
+
+        let ash = mat_db.get(mat_db.get_id("test:ash").unwrap()).unwrap();
+        assert_eq!(ash.decay.len(), 1);
+        assert_eq!(ash.decay[0].out_id, mat_db.get_id("test:air").unwrap());
+        assert_eq!(ash.decay[0].chance, 1.0); // Clamped.
+        assert_eq!(ash.decay[0].min_age, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid material reference")]
+    fn test_decay_invalid_product() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"{ "test:ash": ( decay: [ ( out_name: "test:dust", chance: 0.1 ) ] ) }"#).unwrap();
+    }
+
     #[test]
     fn test_ensure_db_starts_empty() {
         let mat_db = MaterialDb::new();
@@ -0,0 +1,152 @@
+use crate::material::{MaterialId, Thermostat};
+use crate::physics::intent::CellIntent;
+use serde::{Deserialize, Serialize};
+
+/// An area of the world targeted by a command. Cells outside the world are ignored.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Region {
+    Cell { x: usize, y: usize },
+    /// Axis-aligned rectangle with its top-left corner at (x, y).
+    Rect { x: usize, y: usize, w: usize, h: usize },
+    /// All cells within `r` of (cx, cy), inclusive.
+    Circle { cx: usize, cy: usize, r: usize },
+}
+
+impl Region {
+    /// Square of side `2 * r + 1` centered on (cx, cy), clipped at the world's top/left edge.
+    pub fn square(cx: usize, cy: usize, r: usize) -> Self {
+        let x = cx.saturating_sub(r);
+        let y = cy.saturating_sub(r);
+        Region::Rect { x, y, w: cx.saturating_add(r).saturating_add(1) - x, h: cy.saturating_add(r).saturating_add(1) - y }
+    }
+
+    /// Cells inside the region, clipped to a `w` x `h` world, in row-major order.
+    pub fn cells(&self, w: usize, h: usize) -> Vec<(usize, usize)> {
+        let mut cells = vec![];
+        match *self {
+            Region::Cell { x, y } => {
+                if x < w && y < h {
+                    cells.push((x, y));
+                }
+            }
+            Region::Rect { x, y, w: rw, h: rh } => {
+                for cy in y..y.saturating_add(rh).min(h) {
+                    for cx in x..x.saturating_add(rw).min(w) {
+                        cells.push((cx, cy));
+                    }
+                }
+            }
+            Region::Circle { cx, cy, r } => {
+                // Squared distances are taken wide enough that no region can overflow them.
+                let r2 = r as u128 * r as u128;
+                for y in cy.saturating_sub(r)..cy.saturating_add(r).saturating_add(1).min(h) {
+                    for x in cx.saturating_sub(r)..cx.saturating_add(r).saturating_add(1).min(w) {
+                        let dx = x.abs_diff(cx) as u128;
+                        let dy = y.abs_diff(cy) as u128;
+                        if (dx * dx).saturating_add(dy * dy) <= r2 {
+                            cells.push((x, y));
+                        }
+                    }
+                }
+            }
+        }
+        cells
+    }
+}
+
+/// An edit to the world requested from outside the Sim thread (UI, game logic, scripts).
+/// Commands are queued on the engine and applied at the start of the next step, one after
+/// another and ahead of all module intents, or between ticks with `Engine::apply_commands`
+/// while paused. Because they are applied at a tick boundary and in queue order, recording
+/// `(tick, command)` pairs is enough to replay a session exactly.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WorldCommand {
+    /// Set material of every cell in the region, and optionally its temperature.
+    PlaceMaterial { region: Region, mat: MaterialId, temp: Option<f32> },
+    SetTemp { region: Region, temp: f32 },
+    AddTemp { region: Region, delta: f32 },
+    /// Push the fluid in every cell in the region, in cells per tick. Stirs up currents and gusts.
+    AddVelocity { region: Region, vx: f32, vy: f32 },
+    /// Swap the contents (material/temperature) of two cells.
+    Swap { a: (usize, usize), b: (usize, usize) },
+    /// Replace every cell in the region with the empty material, keeping its temperature.
+    Clear { region: Region },
+    /// Mark every cell in the region as a thermostatic source, or unmark it with None.
+    SetSource { region: Region, source: Option<Thermostat> },
+    /// Set off a blast at (x, y), as if an explosive there had detonated. The cell at (x, y)
+    /// and blown-apart cells are left empty.
+    Detonate { x: usize, y: usize, radius: u16, power: f32, heat: f32 },
+}
+
+impl WorldCommand {
+    /// Convert to intents for a `w` x `h` world. `empty` is the material used by `Clear` and `Detonate`.
+    pub fn to_intents(&self, w: usize, h: usize, empty: MaterialId, intents: &mut Vec<CellIntent>) {
+        match *self {
+            WorldCommand::PlaceMaterial { region, mat, temp } => {
+                intents.extend(region.cells(w, h).into_iter().map(|cell| CellIntent::Place { cell, out: mat, temp }));
+            }
+            WorldCommand::SetTemp { region, temp } => {
+                intents.extend(region.cells(w, h).into_iter().map(|cell| CellIntent::SetTemp { cell, temp }));
+            }
+            WorldCommand::AddTemp { region, delta } => {
+                intents.extend(region.cells(w, h).into_iter().map(|cell| CellIntent::AddTemp { cell, delta }));
+            }
+            WorldCommand::AddVelocity { region, vx, vy } => {
+                intents.extend(region.cells(w, h).into_iter().map(|cell| CellIntent::AddVelocity { cell, vx, vy }));
+            }
+            WorldCommand::Swap { a, b } => {
+                if a != b && a.0 < w && a.1 < h && b.0 < w && b.1 < h {
+                    intents.push(CellIntent::MoveSwap { from: a, to: b });
+                }
+            }
+            WorldCommand::Clear { region } => {
+                intents.extend(region.cells(w, h).into_iter().map(|cell| CellIntent::Place { cell, out: empty, temp: None }));
+            }
+            WorldCommand::Detonate { x, y, radius, power, heat } => {
+                if x < w && y < h {
+                    intents.push(CellIntent::Blast { center: (x, y), radius, power, heat, fill: empty });
+                }
+            }
+            WorldCommand::SetSource { .. } => {
+                // Not a cell edit. The engine applies it to the world directly.
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_rect_clipped() {
+        let cells = Region::Rect { x: 2, y: 1, w: 5, h: 2 }.cells(4, 4);
+        assert_eq!(cells, vec![(2, 1), (3, 1), (2, 2), (3, 2)]);
+    }
+
+    #[test]
+    fn test_region_circle() {
+        let cells = Region::Circle { cx: 2, cy: 2, r: 1 }.cells(5, 5);
+        assert_eq!(cells, vec![(2, 1), (1, 2), (2, 2), (3, 2), (2, 3)]);
+
+        // Clipped at the world edge.
+        let cells = Region::Circle { cx: 0, cy: 0, r: 1 }.cells(5, 5);
+        assert_eq!(cells, vec![(0, 0), (1, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn test_region_square() {
+        assert_eq!(Region::square(5, 5, 1), Region::Rect { x: 4, y: 4, w: 3, h: 3 });
+        assert_eq!(Region::square(0, 1, 2), Region::Rect { x: 0, y: 0, w: 3, h: 4 });
+        assert!(Region::Cell { x: 9, y: 0 }.cells(4, 4).is_empty());
+    }
+
+    #[test]
+    fn test_region_huge_does_not_overflow() {
+        let all: Vec<_> = (0..4).flat_map(|y| (0..4).map(move |x| (x, y))).collect();
+        assert_eq!(Region::Rect { x: 0, y: 0, w: usize::MAX, h: usize::MAX }.cells(4, 4), all);
+        assert_eq!(Region::Circle { cx: 2, cy: 2, r: usize::MAX }.cells(4, 4), all);
+        assert!(Region::Circle { cx: usize::MAX, cy: usize::MAX, r: 1 }.cells(4, 4).is_empty());
+        assert_eq!(Region::square(usize::MAX, 0, usize::MAX), Region::Rect { x: 0, y: 0, w: usize::MAX, h: usize::MAX });
+    }
+}
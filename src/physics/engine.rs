@@ -1,25 +1,67 @@
-﻿use crate::physics::intent::CellIntent;
+﻿use crate::material::{MaterialDb, MaterialId, Phase};
+use crate::physics::command::{Region, WorldCommand};
+use crate::physics::intent::CellIntent;
 use crate::physics::module::{Module, ModuleOutput};
 use crate::world::{CurrCtx, NextCtx, World};
+use anyhow::{ensure, Context, Result};
 use rayon::prelude::{IntoParallelRefMutIterator, ParallelIterator};
 use serde_json::Value;
 use std::collections::HashMap;
 
+/// Material that `WorldCommand::Clear` fills cells with, unless config sets `empty_material`.
+pub const DEFAULT_EMPTY_MATERIAL: &str = "base:air";
+
+/// Read `empty_material` from config. Fails if it is not a string or names a missing material.
+pub fn empty_material_from_config(config: &HashMap<String, Value>, mat_db: &MaterialDb) -> Result<MaterialId> {
+    let name = match config.get("empty_material") {
+        Some(v) => v.as_str().context("Invalid config: empty_material must be a string")?,
+        None => DEFAULT_EMPTY_MATERIAL,
+    };
+    mat_db.get_id(name).with_context(|| format!("Missing material: {}", name))
+}
+
 pub struct Engine {
     modules: Vec<Box<dyn Module + Send>>,
     config: HashMap<String, Value>,
     changed_dense: Vec<bool>,
     changed_sparse: Vec<usize>,
+
+    /// External edits waiting for the next step.
+    commands: Vec<WorldCommand>,
+    /// Material used by `Clear` and `Detonate`.
+    empty_material: MaterialId,
 }
 
 impl Engine {
-    pub fn new(config: HashMap<String, Value>, world_w: usize, world_h: usize) -> Self {
-        Self {
+    /// Fails if config `empty_material` is invalid or missing from `mat_db`.
+    pub fn new(config: HashMap<String, Value>, mat_db: &MaterialDb, world_w: usize, world_h: usize) -> Result<Self> {
+        let empty_material = empty_material_from_config(&config, mat_db)?;
+        Ok(Self {
             modules: vec![],
             config,
             changed_dense: vec![false; world_w * world_h],
             changed_sparse: vec![],
-        }
+            commands: vec![],
+            empty_material,
+        })
+    }
+
+    /// Material that cleared and blasted cells are filled with, from config `empty_material`.
+    pub fn empty_material(&self) -> MaterialId {
+        self.empty_material
+    }
+
+    /// Queue an external edit. Queued commands are applied at the start of the next step, one
+    /// after another in queue order, so later commands build on earlier ones (placing a
+    /// material then setting its temperature, or stacking heat). Cells they touch are locked
+    /// against module intents for that step.
+    pub fn queue_command(&mut self, cmd: WorldCommand) {
+        self.commands.push(cmd);
+    }
+
+    /// Whether any commands are waiting to be applied.
+    pub fn has_commands(&self) -> bool {
+        !self.commands.is_empty()
     }
 
     pub fn add<M: Module + 'static>(&mut self, mut m: M) {
@@ -27,7 +69,45 @@ impl Engine {
         self.modules.push(Box::new(m));
     }
 
+    /// Number of cells this engine was built for.
+    pub fn cell_count(&self) -> usize {
+        self.changed_dense.len()
+    }
+
+    /// Serialized state of every module, in module order.
+    pub fn save_module_states(&self) -> Result<Vec<Vec<u8>>> {
+        self.modules.iter().map(|m| m.save_state()).collect()
+    }
+
+    /// Restore module states produced by `save_module_states`. The engine must have
+    /// been built with the same modules, added in the same order.
+    pub fn load_module_states(&mut self, states: &[Vec<u8>]) -> Result<()> {
+        ensure!(states.len() == self.modules.len(),
+            "saved state has {} modules, engine has {}", states.len(), self.modules.len());
+        for (m, bytes) in self.modules.iter_mut().zip(states) {
+            m.load_state(bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Apply queued commands right away, between ticks, without running modules or advancing
+    /// the tick count. Used to show edits while the clock is paused.
+    pub fn apply_commands(&mut self, world: &mut World) {
+        if self.commands.is_empty() {
+            return;
+        }
+        self.apply_source_commands(world);
+        world.sync_all();
+        {
+            let (curr, mut next) = world.ctx_pair();
+            self.apply_command_intents(&curr, &mut next);
+        }
+        self.finish_step(world);
+        world.swap_all();
+    }
+
     pub fn step(&mut self, world: &mut World) {
+        self.apply_source_commands(world);
 
         // Copy curr buffer to next buffer.
         world.sync_all();
@@ -35,6 +115,12 @@ impl Engine {
         // Get world contexts.
         let (curr, mut next) = world.ctx_pair();
 
+        // Age every cell. Cells that change material this tick are reset to zero below.
+        next.age_all();
+
+        // Drain external commands first, so they win any conflict with module intents.
+        self.apply_command_intents(&curr, &mut next);
+
         // Gather intents from modules in parallel.
         // Gather order is deterministic within modules.
         // Intents are applied in the same order as they were gathered.
@@ -53,9 +139,55 @@ impl Engine {
                 ModuleOutput::DeltaTemp { delta_temp } => {
                     self.apply_delta_temp(&curr, &mut next, &delta_temp);
                 }
+                ModuleOutput::DeltaGasMass { delta_mass } => {
+                    self.apply_delta_gas_mass(&curr, &mut next, &delta_mass);
+                }
+                ModuleOutput::DeltaVelocity { delta_vx, delta_vy } => {
+                    self.apply_delta_velocity(&curr, &mut next, &delta_vx, &delta_vy);
+                }
+            }
+        }
+
+        self.finish_step(world);
+
+        // Commit the frame.
+        world.swap_all();
+        world.tick_count += 1;
+    }
+
+    /// Source edits change world state that is not double-buffered, so they apply directly.
+    fn apply_source_commands(&self, world: &mut World) {
+        for cmd in &self.commands {
+            if let WorldCommand::SetSource { region, source } = *cmd {
+                for (x, y) in region.cells(world.w, world.h) {
+                    world.set_source(x, y, source);
+                }
             }
         }
+    }
+
+    /// Drain queued commands into the next buffer, locking the cells they touch.
+    fn apply_command_intents(&mut self, curr: &CurrCtx<'_>, next: &mut NextCtx<'_>) {
+        if self.commands.is_empty() {
+            return;
+        }
 
+        let mut intents = vec![];
+        for cmd in std::mem::take(&mut self.commands) {
+            cmd.to_intents(curr.w, curr.h, self.empty_material, &mut intents);
+        }
+
+        // Commands never conflict with each other. Each applies on top of the last.
+        for intent in &intents {
+            for (x, y) in intent.affected_cells(curr.w, curr.h) {
+                self.mark_changed(y * curr.w + x);
+            }
+            apply_intent(curr, next, intent);
+        }
+    }
+
+    /// Let modules see the cells changed this step, then reset the changed flags.
+    fn finish_step(&mut self, world: &World) {
         // Get post-run context.
         let post = world.ctx_post_run();
 
@@ -69,15 +201,12 @@ impl Engine {
             self.changed_dense[i] = false;
         }
         self.changed_sparse.clear();
-
-        // Commit the frame.
-        world.swap_all();
     }
 
     fn apply_intents(&mut self, curr: &CurrCtx<'_>, next: &mut NextCtx<'_>, intents: &[CellIntent]) {
 
         for intent in intents {
-            let cells = intent.affected_cells();
+            let cells = intent.affected_cells(curr.w, curr.h);
 
             // Check if any involved cell was already changed this frame.
             if cells.iter().any(|(x, y)| self.changed_dense[y * curr.w + x]) {
@@ -86,42 +215,167 @@ impl Engine {
 
             // Mark cells as changed.
             for (x, y) in &cells {
-                let i = y * curr.w + x;
-                self.changed_dense[i] = true;
-                self.changed_sparse.push(i);
+                self.mark_changed(y * curr.w + x);
             }
 
-            // Apply the action.
-            match intent {
-                &CellIntent::Transform { cell, out } => {
-                    next.set_mat_id(cell.0, cell.1, out);
-                },
-                &CellIntent::Reaction { cell_a, cell_b, out_a, out_b } => {
-                    next.set_mat_id(cell_a.0, cell_a.1, out_a);
-                    next.set_mat_id(cell_b.0, cell_b.1, out_b);
-                },
-                &CellIntent::MoveSwap { from, to } => {
-                    let mat_from = curr.get_mat_id(from.0, from.1);
-                    let mat_to = curr.get_mat_id(to.0, to.1);
-                    next.set_mat_id(from.0, from.1, mat_to);
-                    next.set_mat_id(to.0, to.1, mat_from);
-
-                    // Peek future temp, because we want to make sure temp changes affect this particle.
-                    // Note that we do not peek future mat id. That's because mat id changes
-                    // prevent move swap intents from being applied in the same tick.
-                    let temp_from = next.peek_future_temp(from.0, from.1);
-                    let temp_to = next.peek_future_temp(to.0, to.1);
-                    next.set_temp(from.0, from.1, temp_to);
-                    next.set_temp(to.0, to.1, temp_from);
-                },
-            }
+            apply_intent(curr, next, intent);
+        }
+    }
+
+    /// Lock a cell against later intents this step.
+    fn mark_changed(&mut self, i: usize) {
+        if (!self.changed_dense[i]) {
+            self.changed_dense[i] = true;
+            self.changed_sparse.push(i);
         }
     }
 
     fn apply_delta_temp(&self, curr: &CurrCtx<'_>, next: &mut NextCtx<'_>, delta_temp: &[f32]) {
-        for i in 0..(curr.w * curr.h) {
-            next.add_temp_i(i, delta_temp[i]);
+        for (i, &delta) in delta_temp.iter().enumerate().take(curr.w * curr.h) {
+            next.add_temp_i(i, delta);
+        }
+    }
+
+    fn apply_delta_gas_mass(&self, curr: &CurrCtx<'_>, next: &mut NextCtx<'_>, delta_mass: &[f32]) {
+        for (i, &delta) in delta_mass.iter().enumerate().take(curr.w * curr.h) {
+            next.add_gas_mass_i(i, delta);
+        }
+    }
+
+    fn apply_delta_velocity(&self, curr: &CurrCtx<'_>, next: &mut NextCtx<'_>, delta_vx: &[f32], delta_vy: &[f32]) {
+        for (i, (&vx, &vy)) in delta_vx.iter().zip(delta_vy).enumerate().take(curr.w * curr.h) {
+            next.add_vel_i(i, vx, vy);
+        }
+    }
+}
+
+/// Apply a single intent to the next buffer.
+fn apply_intent(curr: &CurrCtx<'_>, next: &mut NextCtx<'_>, intent: &CellIntent) {
+    match *intent {
+        CellIntent::Transform { cell, out } => {
+            next.set_mat_id(cell.0, cell.1, out);
+            next.reset_cell_state(cell.0, cell.1);
+        },
+        CellIntent::Reaction { cell_a, cell_b, out_a, out_b, heat_a, heat_b, .. } => {
+            // A participant that keeps its material (such as a plant growing) keeps its state.
+            if (out_a != curr.get_mat_id(cell_a.0, cell_a.1)) { next.reset_cell_state(cell_a.0, cell_a.1); }
+            if (out_b != curr.get_mat_id(cell_b.0, cell_b.1)) { next.reset_cell_state(cell_b.0, cell_b.1); }
+            next.set_mat_id(cell_a.0, cell_a.1, out_a);
+            next.set_mat_id(cell_b.0, cell_b.1, out_b);
+            next.add_temp(cell_a.0, cell_a.1, heat_a);
+            next.add_temp(cell_b.0, cell_b.1, heat_b);
+        },
+        CellIntent::LatentHeat { cell, delta_temp, latent, out } => {
+            next.add_temp(cell.0, cell.1, delta_temp);
+            if let Some(out) = out {
+                next.set_mat_id(cell.0, cell.1, out);
+                next.reset_cell_state(cell.0, cell.1);
+            } else {
+                next.set_latent(cell.0, cell.1, latent);
+            }
+        },
+        CellIntent::Ignite { cell, fire } => {
+            let fuel = curr.get_mat_id(cell.0, cell.1);
+            next.set_mat_id(cell.0, cell.1, fire);
+            next.reset_cell_state(cell.0, cell.1);
+            next.set_fuel(cell.0, cell.1, fuel);
+        },
+        CellIntent::Blast { center, radius, power, heat, fill } => {
+            apply_blast(curr, next, center, radius, power, heat, fill);
+        },
+        CellIntent::MoveSwap { from, to } => {
+            // Peek future state, because we want to make sure changes made earlier this tick
+            // (such as temp changes, or a command placing material) move with this particle.
+            let mat_from = next.peek_future_mat_id(from.0, from.1);
+            let mat_to = next.peek_future_mat_id(to.0, to.1);
+            next.set_mat_id(from.0, from.1, mat_to);
+            next.set_mat_id(to.0, to.1, mat_from);
+
+            let temp_from = next.peek_future_temp(from.0, from.1);
+            let temp_to = next.peek_future_temp(to.0, to.1);
+            next.set_temp(from.0, from.1, temp_to);
+            next.set_temp(to.0, to.1, temp_from);
+
+            let age_from = next.peek_future_age(from.0, from.1);
+            let age_to = next.peek_future_age(to.0, to.1);
+            next.set_age(from.0, from.1, age_to);
+            next.set_age(to.0, to.1, age_from);
+
+            let latent_from = next.peek_future_latent(from.0, from.1);
+            let latent_to = next.peek_future_latent(to.0, to.1);
+            next.set_latent(from.0, from.1, latent_to);
+            next.set_latent(to.0, to.1, latent_from);
+
+            let fuel_from = next.peek_future_fuel(from.0, from.1);
+            let fuel_to = next.peek_future_fuel(to.0, to.1);
+            next.set_fuel(from.0, from.1, fuel_to);
+            next.set_fuel(to.0, to.1, fuel_from);
+
+            let mass_from = next.peek_future_gas_mass(from.0, from.1);
+            let mass_to = next.peek_future_gas_mass(to.0, to.1);
+            next.set_gas_mass(from.0, from.1, mass_to);
+            next.set_gas_mass(to.0, to.1, mass_from);
+        },
+        CellIntent::Place { cell, out, temp } => {
+            next.set_mat_id(cell.0, cell.1, out);
+            next.reset_cell_state(cell.0, cell.1);
+            if let Some(temp) = temp {
+                next.set_temp(cell.0, cell.1, temp);
+            }
+        },
+        CellIntent::SetTemp { cell, temp } => {
+            next.set_temp(cell.0, cell.1, temp);
+        },
+        CellIntent::AddTemp { cell, delta } => {
+            next.add_temp(cell.0, cell.1, delta);
+        },
+        CellIntent::AddVelocity { cell, vx, vy } => {
+            next.add_vel_i(cell.1 * curr.w + cell.0, vx, vy);
+        },
+    }
+}
+
+/// Resolve a blast. Every cell within `radius` is heated, and non-gas cells softer than the
+/// blast power where they stand are blown apart, leaving `fill` behind. The center is the
+/// explosive itself, which is always consumed, whatever its phase or hardness. Each blown-apart cell
+/// is flung straight away from the center, landing as debris on the rim one cell past the
+/// radius if there is open gas there. Otherwise it is destroyed.
+fn apply_blast(curr: &CurrCtx<'_>, next: &mut NextCtx<'_>, center: (usize, usize), radius: u16, power: f32, heat: f32, fill: MaterialId) {
+    let reach = radius as f32 + 1.0;
+    let rim = radius as isize + 1;
+    let mut landed: Vec<(usize, usize)> = vec![];
+
+    for (x, y) in (Region::Circle { cx: center.0, cy: center.1, r: radius as usize }).cells(curr.w, curr.h) {
+        let dx = x as f32 - center.0 as f32;
+        let dy = y as f32 - center.1 as f32;
+        let dist = (dx * dx + dy * dy).sqrt();
+        let falloff = 1.0 - dist / reach;
+        next.add_temp(x, y, heat * falloff);
+
+        let id = next.peek_future_mat_id(x, y);
+        if (dist > 0.0 && (curr.mat_db.phase_of(id) == Phase::Gas || power * falloff <= curr.mat_db.hardness_of(id))) {
+            continue;
         }
+
+        let temp = next.peek_future_temp(x, y);
+        next.set_mat_id(x, y, fill);
+        next.reset_cell_state(x, y);
+        if (dist == 0.0) { continue; }
+
+        // Fling to the rim. Rounding can land outside the locked area or back inside the
+        // crater, in which case the debris is lost.
+        let rx = center.0 as isize + (dx / dist * reach).round() as isize;
+        let ry = center.1 as isize + (dy / dist * reach).round() as isize;
+        let (ox, oy) = (rx - center.0 as isize, ry - center.1 as isize);
+        let d2 = ox * ox + oy * oy;
+        if (!curr.contains(rx, ry) || d2 > rim * rim || d2 <= (rim - 1) * (rim - 1)) { continue; }
+
+        let (rx, ry) = (rx as usize, ry as usize);
+        if (landed.contains(&(rx, ry)) || curr.mat_db.phase_of(next.peek_future_mat_id(rx, ry)) != Phase::Gas) { continue; }
+        landed.push((rx, ry));
+        next.set_mat_id(rx, ry, id);
+        next.reset_cell_state(rx, ry);
+        next.set_temp(rx, ry, temp);
     }
 }
 
@@ -163,6 +417,8 @@ mod tests {
             *self.post_run_called.lock().unwrap() = true;
             *self.received_changed_cells.lock().unwrap() = changed_cells.to_vec();
         }
+        fn save_state(&self) -> Result<Vec<u8>> { Ok(vec![]) }
+        fn load_state(&mut self, _bytes: &[u8]) -> Result<()> { Ok(()) }
     }
 
     /// Test helper.
@@ -170,7 +426,7 @@ mod tests {
         let mut mat_db = MaterialDb::new();
         mat_db.load_ron_str(r#"
             {
-                "test:air": (),
+                "base:air": (),
                 "test:water": (),
                 "test:rock": (),
             }
@@ -181,7 +437,7 @@ mod tests {
         {
             let (_, mut next) = world.ctx_pair();
 
-            let mat_id_air = mat_db.get_id("test:air").unwrap();
+            let mat_id_air = mat_db.get_id("base:air").unwrap();
             next.set_mat_id(0, 0, mat_id_air);
             next.set_mat_id(0, 1, mat_id_air);
             next.set_mat_id(1, 0, mat_id_air);
@@ -199,7 +455,7 @@ mod tests {
         assert_eq!(world.w, 2);
         assert_eq!(world.h, 2);
 
-        let mat_id_air = mat_db.get_id("test:air").unwrap();
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
         assert_eq!(world.cell_mat_ids.cur[0], mat_id_air);
         assert_eq!(world.cell_mat_ids.cur[1], mat_id_air);
         assert_eq!(world.cell_mat_ids.cur[2], mat_id_air);
@@ -216,9 +472,9 @@ mod tests {
     #[test]
     fn test_engine_intent_transform() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
-        let mat_id_air = mat_db.get_id("test:air").unwrap();
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
         let mat_id_water = mat_db.get_id("test:water").unwrap();
 
         let intent = CellIntent::Transform {
@@ -240,9 +496,9 @@ mod tests {
     #[test]
     fn test_engine_intent_reaction() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
-        let mat_id_air = mat_db.get_id("test:air").unwrap();
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
         let mat_id_water = mat_db.get_id("test:water").unwrap();
 
         let intent = CellIntent::Reaction {
@@ -250,6 +506,9 @@ mod tests {
             cell_b: (0, 1),
             out_a: mat_id_water,
             out_b: mat_id_water,
+            heat_a: 25.0,
+            heat_b: -10.0,
+            catalyst: None,
         };
 
         // Add module and step engine.
@@ -261,14 +520,20 @@ mod tests {
         assert_eq!(world.cell_mat_ids.cur[1], mat_id_water);
         assert_eq!(world.cell_mat_ids.cur[2], mat_id_water);
         assert_eq!(world.cell_mat_ids.cur[3], mat_id_air);
+
+        // Ensure reaction heat was applied to each product cell.
+        assert_eq!(world.cell_temps.cur[0], 0.0);
+        assert_eq!(world.cell_temps.cur[1], 25.0);
+        assert_eq!(world.cell_temps.cur[2], -10.0);
+        assert_eq!(world.cell_temps.cur[3], 0.0);
     }
 
     #[test]
     fn test_engine_intent_move_swap() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
-        let mat_id_air = mat_db.get_id("test:air").unwrap();
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
         let mat_id_water = mat_db.get_id("test:water").unwrap();
         let mat_id_rock = mat_db.get_id("test:rock").unwrap();
 
@@ -303,7 +568,7 @@ mod tests {
     #[test]
     fn test_engine_delta_temp() {
         let (mut world, _) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
         let delta_temp = vec![1.0, 2.0, 3.0, 4.0];
         engine.add(MockModule::new(Some(ModuleOutput::DeltaTemp { delta_temp })));
@@ -323,7 +588,7 @@ mod tests {
     #[test]
     fn test_engine_intent_transform_twice_conflict() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
         let mat_id_water = mat_db.get_id("test:water").unwrap();
         let mat_id_rock = mat_db.get_id("test:rock").unwrap();
@@ -347,20 +612,20 @@ mod tests {
     #[test]
     fn test_engine_intent_reaction_twice_conflict() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
-        let mat_id_air = mat_db.get_id("test:air").unwrap();
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
         let mat_id_water = mat_db.get_id("test:water").unwrap();
         let mat_id_rock = mat_db.get_id("test:rock").unwrap();
 
         // First module reacts (0,1) and (1,0) to Water.
         engine.add(MockModule::new(Some(ModuleOutput::CellIntents {
-            intents: vec![CellIntent::Reaction { cell_a: (0, 1), cell_b: (1, 0), out_a: mat_id_water, out_b: mat_id_water }]
+            intents: vec![CellIntent::Reaction { cell_a: (0, 1), cell_b: (1, 0), out_a: mat_id_water, out_b: mat_id_water, heat_a: 0.0, heat_b: 0.0, catalyst: None }]
         })));
 
         // Second module reacts (1,0) and (1,1) to Rock. BOTH should be ignored/discarded, as reactions are atomic.
         engine.add(MockModule::new(Some(ModuleOutput::CellIntents {
-            intents: vec![CellIntent::Reaction { cell_a: (1, 0), cell_b: (1, 1), out_a: mat_id_rock, out_b: mat_id_rock }]
+            intents: vec![CellIntent::Reaction { cell_a: (1, 0), cell_b: (1, 1), out_a: mat_id_rock, out_b: mat_id_rock, heat_a: 0.0, heat_b: 0.0, catalyst: None }]
         })));
 
         engine.step(&mut world);
@@ -372,12 +637,40 @@ mod tests {
         assert_eq!(world.cell_mat_ids.cur[3], mat_id_air); // Shouldn't change to rock because reaction should be blocked.
     }
 
+    #[test]
+    fn test_engine_intent_reaction_catalyst_locked() {
+        let (mut world, mat_db) = mock_world(2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
+
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
+        let mat_id_water = mat_db.get_id("test:water").unwrap();
+        let mat_id_rock = mat_db.get_id("test:rock").unwrap();
+
+        // First module reacts (0,1) and (1,0) to Water, catalyzed by (0,0).
+        engine.add(MockModule::new(Some(ModuleOutput::CellIntents {
+            intents: vec![CellIntent::Reaction { cell_a: (0, 1), cell_b: (1, 0), out_a: mat_id_water, out_b: mat_id_water, heat_a: 0.0, heat_b: 0.0, catalyst: Some((0, 0)) }]
+        })));
+
+        // Second module transforms the catalyst to Rock. Blocked, as the catalyst is locked.
+        engine.add(MockModule::new(Some(ModuleOutput::CellIntents {
+            intents: vec![CellIntent::Transform { cell: (0, 0), out: mat_id_rock }]
+        })));
+
+        engine.step(&mut world);
+
+        // Catalyst is neither consumed nor transformed.
+        assert_eq!(world.cell_mat_ids.cur[0], mat_id_air);
+        assert_eq!(world.cell_mat_ids.cur[1], mat_id_water);
+        assert_eq!(world.cell_mat_ids.cur[2], mat_id_water);
+        assert_eq!(world.cell_mat_ids.cur[3], mat_id_air);
+    }
+
     #[test]
     fn test_engine_intent_move_twice_conflict() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
-        let mat_id_air = mat_db.get_id("test:air").unwrap();
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
         let mat_id_water = mat_db.get_id("test:water").unwrap();
         let mat_id_rock = mat_db.get_id("test:rock").unwrap();
 
@@ -416,7 +709,7 @@ mod tests {
     #[test]
     fn test_engine_delta_temp_twice_overlap() {
         let (mut world, _) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
         // Both modules add to temp of each cell.
         let delta_temp = vec![1.0, 2.0, 3.0, 4.0];
@@ -437,9 +730,9 @@ mod tests {
     #[test]
     fn test_engine_intent_transform_move_conflict() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
-        let mat_id_air = mat_db.get_id("test:air").unwrap();
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
         let mat_id_water = mat_db.get_id("test:water").unwrap();
 
         // First module transforms (1,0) to Water.
@@ -462,14 +755,14 @@ mod tests {
     #[test]
     fn test_engine_intent_reaction_move_conflict() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
-        let mat_id_air = mat_db.get_id("test:air").unwrap();
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
         let mat_id_water = mat_db.get_id("test:water").unwrap();
 
         // First module reacts (1,0) and (0,1) to Water.
         engine.add(MockModule::new(Some(ModuleOutput::CellIntents {
-            intents: vec![CellIntent::Reaction { cell_a: (1, 0), cell_b: (0, 1), out_a: mat_id_water, out_b: mat_id_water }]
+            intents: vec![CellIntent::Reaction { cell_a: (1, 0), cell_b: (0, 1), out_a: mat_id_water, out_b: mat_id_water, heat_a: 0.0, heat_b: 0.0, catalyst: None }]
         })));
 
         // Second module swaps (1,0) and (0,0). Should be ignored/discarded.
@@ -489,7 +782,7 @@ mod tests {
     #[test]
     fn test_engine_intent_temp_move_overlap() {
         let (mut world, _) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
         // First module adds 500.0 to (1,0)'s temperature.
         let delta_temp = vec![0.0, 500.0, 0.0, 0.0];
@@ -511,7 +804,7 @@ mod tests {
     #[test]
     fn test_engine_intent_transform_temp_overlap() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
         let mat_id_water = mat_db.get_id("test:water").unwrap();
 
@@ -534,13 +827,13 @@ mod tests {
     #[test]
     fn test_engine_intent_reaction_temp_overlap() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
         let mat_id_water = mat_db.get_id("test:water").unwrap();
 
         // First module reacts (0,1) and (1,0) to Water.
         engine.add(MockModule::new(Some(ModuleOutput::CellIntents {
-            intents: vec![CellIntent::Reaction { cell_a: (0, 1), cell_b: (1, 0), out_a: mat_id_water, out_b: mat_id_water }]
+            intents: vec![CellIntent::Reaction { cell_a: (0, 1), cell_b: (1, 0), out_a: mat_id_water, out_b: mat_id_water, heat_a: 0.0, heat_b: 0.0, catalyst: None }]
         })));
 
         // Second module adds 500.0 to (0,1) temp and 400.0 to (1,0) temp.
@@ -560,9 +853,9 @@ mod tests {
     #[test]
     fn test_engine_intent_transform_reaction_conflict() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
-        let mat_id_air = mat_db.get_id("test:air").unwrap();
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
         let mat_id_water = mat_db.get_id("test:water").unwrap();
         let mat_id_rock = mat_db.get_id("test:rock").unwrap();
 
@@ -573,7 +866,7 @@ mod tests {
 
         // Second module reacts (1,0) and (1,1) to Rock. BOTH should be ignored/discarded, as reactions are atomic.
         engine.add(MockModule::new(Some(ModuleOutput::CellIntents {
-            intents: vec![CellIntent::Reaction { cell_a: (1, 0), cell_b: (1, 1), out_a: mat_id_rock, out_b: mat_id_rock }]
+            intents: vec![CellIntent::Reaction { cell_a: (1, 0), cell_b: (1, 1), out_a: mat_id_rock, out_b: mat_id_rock, heat_a: 0.0, heat_b: 0.0, catalyst: None }]
         })));
 
         engine.step(&mut world);
@@ -585,11 +878,138 @@ mod tests {
         assert_eq!(world.cell_mat_ids.cur[3], mat_id_air);
     }
 
+    // World command tests.
+    #[test]
+    fn test_engine_command_place_material() {
+        let (mut world, mat_db) = mock_world(2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
+
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
+        let mat_id_water = mat_db.get_id("test:water").unwrap();
+
+        // Place water on the top row, at 80 degrees.
+        engine.queue_command(WorldCommand::PlaceMaterial {
+            region: Region::Rect { x: 0, y: 0, w: 2, h: 1 },
+            mat: mat_id_water,
+            temp: Some(80.0),
+        });
+        engine.step(&mut world);
+
+        assert_eq!(world.cell_mat_ids.cur[0], mat_id_water);
+        assert_eq!(world.cell_mat_ids.cur[1], mat_id_water);
+        assert_eq!(world.cell_mat_ids.cur[2], mat_id_air);
+        assert_eq!(world.cell_mat_ids.cur[3], mat_id_air);
+        assert_eq!(world.cell_temps.cur[0], 80.0);
+        assert_eq!(world.cell_temps.cur[1], 80.0);
+        assert_eq!(world.cell_temps.cur[2], 0.0);
+
+        // Commands are consumed by the step that applies them.
+        engine.step(&mut world);
+        assert_eq!(world.cell_mat_ids.cur[2], mat_id_air);
+    }
+
+    #[test]
+    fn test_engine_command_temps() {
+        let (mut world, _) = mock_world(2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
+
+        engine.queue_command(WorldCommand::SetTemp { region: Region::Cell { x: 0, y: 0 }, temp: 300.0 });
+        engine.queue_command(WorldCommand::AddTemp { region: Region::Cell { x: 1, y: 1 }, delta: -40.0 });
+        engine.step(&mut world);
+
+        assert_eq!(world.cell_temps.cur[0], 300.0);
+        assert_eq!(world.cell_temps.cur[3], -40.0);
+    }
+
+    #[test]
+    fn test_engine_commands_apply_in_sequence() {
+        let (mut world, mat_db) = mock_world(2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
+        let mat_id_water = mat_db.get_id("test:water").unwrap();
+
+        // A later command on the same cell builds on an earlier one.
+        engine.queue_command(WorldCommand::PlaceMaterial { region: Region::Cell { x: 0, y: 0 }, mat: mat_id_water, temp: None });
+        engine.queue_command(WorldCommand::SetTemp { region: Region::Cell { x: 0, y: 0 }, temp: 50.0 });
+        engine.step(&mut world);
+        assert_eq!(world.cell_mat_ids.cur[0], mat_id_water);
+        assert_eq!(world.cell_temps.cur[0], 50.0);
+
+        // Heat stacks.
+        engine.queue_command(WorldCommand::AddTemp { region: Region::Cell { x: 1, y: 1 }, delta: 10.0 });
+        engine.queue_command(WorldCommand::AddTemp { region: Region::Cell { x: 1, y: 1 }, delta: 10.0 });
+        engine.step(&mut world);
+        assert_eq!(world.cell_temps.cur[3], 20.0);
+
+        // A swap moves what an earlier command placed.
+        engine.queue_command(WorldCommand::PlaceMaterial { region: Region::Cell { x: 1, y: 0 }, mat: mat_id_water, temp: Some(70.0) });
+        engine.queue_command(WorldCommand::Swap { a: (1, 0), b: (0, 1) });
+        engine.step(&mut world);
+        assert_eq!(world.cell_mat_ids.cur[2], mat_id_water);
+        assert_eq!(world.cell_temps.cur[2], 70.0);
+    }
+
+    #[test]
+    fn test_engine_invalid_empty_material_is_an_error() {
+        let (world, _) = mock_world(2, 2);
+        for name in [Value::from("test:lava"), Value::from(7)] {
+            let mut config = HashMap::new();
+            config.insert("empty_material".to_owned(), name);
+            assert!(Engine::new(config, &world.mat_db, 2, 2).is_err());
+        }
+    }
+
+    #[test]
+    fn test_engine_command_swap_and_clear() {
+        let (mut world, mat_db) = mock_world(2, 2);
+        let mut config = HashMap::new();
+        config.insert("empty_material".to_owned(), Value::from("base:air"));
+        let mut engine = Engine::new(config, &world.mat_db, 2, 2).unwrap();
+
+        let mat_id_air = mat_db.get_id("base:air").unwrap();
+        let mat_id_rock = mat_db.get_id("test:rock").unwrap();
+
+        engine.queue_command(WorldCommand::PlaceMaterial { region: Region::Cell { x: 0, y: 0 }, mat: mat_id_rock, temp: Some(10.0) });
+        engine.step(&mut world);
+
+        // Swap moves material and temperature.
+        engine.queue_command(WorldCommand::Swap { a: (0, 0), b: (1, 1) });
+        engine.step(&mut world);
+        assert_eq!(world.cell_mat_ids.cur[0], mat_id_air);
+        assert_eq!(world.cell_mat_ids.cur[3], mat_id_rock);
+        assert_eq!(world.cell_temps.cur[3], 10.0);
+
+        // Clear replaces with the configured empty material, keeping temperature.
+        engine.queue_command(WorldCommand::Clear { region: Region::Circle { cx: 1, cy: 1, r: 5 } });
+        engine.step(&mut world);
+        assert_eq!(world.cell_mat_ids.cur[3], mat_id_air);
+        assert_eq!(world.cell_temps.cur[3], 10.0);
+    }
+
+    #[test]
+    fn test_engine_command_beats_module_intent() {
+        let (mut world, mat_db) = mock_world(2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
+
+        let mat_id_water = mat_db.get_id("test:water").unwrap();
+        let mat_id_rock = mat_db.get_id("test:rock").unwrap();
+
+        // Module transforms (1,1) to Rock. Should be ignored/discarded.
+        engine.add(MockModule::new(Some(ModuleOutput::CellIntents {
+            intents: vec![CellIntent::Transform { cell: (1, 1), out: mat_id_rock }]
+        })));
+
+        // Command places Water at (1,1). Commands apply before module intents.
+        engine.queue_command(WorldCommand::PlaceMaterial { region: Region::Cell { x: 1, y: 1 }, mat: mat_id_water, temp: None });
+        engine.step(&mut world);
+
+        assert_eq!(world.cell_mat_ids.cur[3], mat_id_water);
+    }
+
     // Post-run tests.
     #[test]
     fn test_engine_post_run_changed_cells_transform() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
         let mat_id_water = mat_db.get_id("test:water").unwrap();
 
@@ -614,13 +1034,13 @@ mod tests {
     #[test]
     fn test_engine_post_run_changed_cells_reaction() {
         let (mut world, mat_db) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
         let mat_id_water = mat_db.get_id("test:water").unwrap();
 
         // First module reacts (0,1) and (1,0) to Water.
         let mock = MockModule::new(Some(ModuleOutput::CellIntents {
-            intents: vec![CellIntent::Reaction { cell_a: (0, 1), cell_b: (1, 0), out_a: mat_id_water, out_b: mat_id_water}]
+            intents: vec![CellIntent::Reaction { cell_a: (0, 1), cell_b: (1, 0), out_a: mat_id_water, out_b: mat_id_water, heat_a: 0.0, heat_b: 0.0, catalyst: None }]
         }));
 
         let post_run_called = Arc::clone(&mock.post_run_called);
@@ -641,7 +1061,7 @@ mod tests {
     #[test]
     fn test_engine_post_run_changed_cells_swap() {
         let (mut world, _) = mock_world(2, 2);
-        let mut engine = Engine::new(HashMap::new(), 2, 2);
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 2).unwrap();
 
         // First module swaps (1,1) and (0,0).
         let mock = MockModule::new(Some(ModuleOutput::CellIntents {
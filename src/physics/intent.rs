@@ -1,11 +1,12 @@
 ﻿use crate::material::MaterialId;
+use crate::physics::command::Region;
 
 /// Intents are lightweight descriptions of changes to the world state, produced by
 /// physics modules that run concurrently. They are processed sequentially by the physics
 /// engine, with care to handle collisions, after all modules run in parallel.
 
 #[derive(Debug, Clone, Copy)]
-pub(crate) enum CellIntent {
+pub enum CellIntent {
     Transform {
         cell: (usize, usize),
         out: MaterialId,
@@ -15,19 +16,71 @@ pub(crate) enum CellIntent {
         cell_b: (usize, usize),
         out_a: MaterialId,
         out_b: MaterialId,
+        heat_a: f32,    // Temperature added to each cell. Negative for endothermic reactions.
+        heat_b: f32,
+        catalyst: Option<(usize, usize)>,  // Not changed, but locked so it can't change this tick.
+    },
+    LatentHeat {    // Moves heat between a cell's temperature and its latent heat store. Produced by phase transitions.
+        cell: (usize, usize),
+        delta_temp: f32,
+        latent: f32,            // New value of the latent heat store. A transform empties it instead.
+        out: Option<MaterialId>,  // Transform, once the store is full.
+    },
+    Ignite {    // Turns a fuel cell into fire, remembering the fuel it burns.
+        cell: (usize, usize),
+        fire: MaterialId,
+    },
+    Blast {     // Atomic: locks every cell it can touch, out to one cell past `radius`, where debris lands.
+        center: (usize, usize),
+        radius: u16,
+        power: f32,     // Blast power at the center. Falls off linearly to zero one cell past `radius`.
+        heat: f32,      // Temperature added at the center, falling off the same way.
+        fill: MaterialId,   // Left behind in cells that are blown apart.
     },
     MoveSwap {
         from: (usize, usize),
         to: (usize, usize),
     },
+    Place {     // Sets material and (optionally) temperature. Produced by world commands.
+        cell: (usize, usize),
+        out: MaterialId,
+        temp: Option<f32>,
+    },
+    SetTemp {
+        cell: (usize, usize),
+        temp: f32,
+    },
+    AddTemp {
+        cell: (usize, usize),
+        delta: f32,
+    },
+    AddVelocity {   // Pushes the fluid in a cell. Has no effect on cells that are not liquid or gas.
+        cell: (usize, usize),
+        vx: f32,
+        vy: f32,
+    },
 }
 
 impl CellIntent {
-    pub(crate) fn affected_cells(&self) -> Vec<(usize, usize)> {
+    /// Cells the intent changes or locks, in a `w` x `h` world.
+    pub fn affected_cells(&self, w: usize, h: usize) -> Vec<(usize, usize)> {
         match self {
             CellIntent::Transform { cell, ..} => {vec![*cell]},
-            CellIntent::Reaction { cell_a, cell_b, .. } => vec![*cell_a, *cell_b],
+            CellIntent::Reaction { cell_a, cell_b, catalyst, .. } => {
+                let mut cells = vec![*cell_a, *cell_b];
+                cells.extend(catalyst);
+                cells
+            },
+            CellIntent::LatentHeat { cell, .. } => vec![*cell],
+            CellIntent::Ignite { cell, .. } => vec![*cell],
+            CellIntent::Blast { center, radius, .. } => {
+                Region::Circle { cx: center.0, cy: center.1, r: *radius as usize + 1 }.cells(w, h)
+            },
             CellIntent::MoveSwap { from, to} => {vec![*from, *to]},
+            CellIntent::Place { cell, .. } => vec![*cell],
+            CellIntent::SetTemp { cell, .. } => vec![*cell],
+            CellIntent::AddTemp { cell, .. } => vec![*cell],
+            CellIntent::AddVelocity { cell, .. } => vec![*cell],
         }
     }
 }
\ No newline at end of file
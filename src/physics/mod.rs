@@ -1,8 +1,19 @@
 ﻿pub mod util;
+pub mod command;
 pub mod engine;
 pub mod module;
-pub mod module_behavior_steam;
+pub mod module_combustion;
+pub mod module_combustion_heat;
 pub mod module_diffusion_thermal;
+pub mod module_explosion;
+pub mod module_fluid;
+pub mod module_fluid_flow;
+pub mod module_gas;
+pub mod module_gravity;
+pub mod module_pressure;
+pub mod module_radiation;
 pub mod module_reactions_basic;
+pub mod module_thermostat;
+pub mod module_transforms_decay;
 pub mod module_transforms_thermal;
 pub mod intent;
\ No newline at end of file
@@ -1,5 +1,6 @@
 ﻿use crate::physics::intent::CellIntent;
 use crate::world::{CurrCtx, PostRunCtx};
+use anyhow::Result;
 use serde_json::Value;
 use std::collections::HashMap;
 
@@ -10,10 +11,22 @@ pub enum ModuleOutput {
     DeltaTemp {
         delta_temp: Vec<f32>,
     },
+    DeltaGasMass {
+        delta_mass: Vec<f32>,
+    },
+    DeltaVelocity {
+        delta_vx: Vec<f32>,
+        delta_vy: Vec<f32>,
+    },
 }
 
 pub trait Module: Send {
     fn apply_config(&mut self, config: &HashMap<String, Value>);
     fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput;
     fn post_run(&mut self, post: &PostRunCtx<'_>, changed_cells: &[usize]);
+
+    /// Serialize everything the module needs to continue bit-identically after a load
+    /// (RNG state, cached values, toggles). Config values are not included.
+    fn save_state(&self) -> Result<Vec<u8>>;
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()>;
 }
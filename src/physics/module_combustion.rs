@@ -0,0 +1,207 @@
+use crate::material::{Material, MaterialId, Phase};
+use crate::physics::intent::CellIntent;
+use crate::physics::module::{Module, ModuleOutput};
+use crate::physics::util::{rand_iter_dir, try_random_dirs};
+use crate::world::{CurrCtx, PostRunCtx};
+use rand::{Rng, SeedableRng};
+use anyhow::Result;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Material that burning cells become, unless config sets `fire_material`.
+pub const DEFAULT_FIRE_MATERIAL: &str = "base:fire";
+
+/// Read the fire material name from config.
+pub fn fire_material_from_config(config: &HashMap<String, Value>) -> String {
+    match config.get("fire_material") {
+        Some(v) => v.as_str().expect("Invalid config: fire_material must be a string").to_owned(),
+        None => DEFAULT_FIRE_MATERIAL.to_owned(),
+    }
+}
+
+/// The material whose combustion properties a fire cell at (x, y) uses: the fuel it is
+/// burning, or the fire material itself if the fuel is not flammable (such as painted fire).
+pub fn burning_fuel<'a>(curr: &CurrCtx<'a>, x: usize, y: usize, fire: MaterialId) -> Option<&'a Material> {
+    let fuel = curr.mat_db.get(curr.get_fuel(x, y))?;
+    if (fuel.burn_ticks > 0) { Some(fuel) } else { curr.mat_db.get(fire) }
+}
+
+/// Ignites flammable cells that reach their `ignition_temp`, turning them into fire that
+/// remembers its fuel. Fire burns for the fuel's `burn_ticks`, puffing smoke into neighboring
+/// gas, then turns into the fuel's `burn_product`. Heat is released by `ModuleCombustionHeat`,
+/// and spreads the fire to flammable neighbors by heating them past their ignition temperature.
+pub struct ModuleCombustion {
+    rng_a: Xoshiro256PlusPlus,
+    rng_b: Xoshiro256PlusPlus,
+    fire_material: String,
+}
+
+impl ModuleCombustion {
+    pub fn new(_curr: &CurrCtx<'_>, rng_seed: u64) -> Self {
+        Self {
+            rng_a: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
+            rng_b: Xoshiro256PlusPlus::seed_from_u64(rng_seed ^ 0xBBBBBBBBBBBBBBBB),
+            fire_material: DEFAULT_FIRE_MATERIAL.to_owned(),
+        }
+    }
+}
+
+impl Module for ModuleCombustion {
+
+    fn apply_config(&mut self, config: &HashMap<String, Value>) {
+        self.fire_material = fire_material_from_config(config);
+    }
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+
+        let mut intents = vec![];
+
+        // Fire material is only required once something ignites.
+        let fire = curr.mat_db.get_id(&self.fire_material);
+
+        rand_iter_dir(&mut self.rng_a, curr.w, curr.h, |x, y| {
+
+            let id = curr.get_mat_id(x, y);
+
+            // Burning cells burn out, or puff smoke.
+            if (Some(id) == fire) {
+                let Some(fuel) = burning_fuel(curr, x, y, id) else { return };
+                if (curr.get_age(x, y) >= fuel.burn_ticks) {
+                    if let Some(product) = fuel.burn_product_id {
+                        intents.push(CellIntent::Transform { cell: (x, y), out: product });
+                    }
+                    return;
+                }
+                if let Some(smoke) = fuel.burn_smoke_id {
+                    if self.rng_b.random_range(0.0..1.0) < fuel.burn_smoke_chance {
+                        try_random_dirs(&mut self.rng_b, true, |(dx, dy)| {
+                            let Some((nx, ny)) = curr.neighbor_cell(x, y, dx, dy) else { return false };
+                            let b = curr.get_mat_id(nx, ny);
+                            if (b != smoke && curr.mat_db.phase_of(b) == Phase::Gas) {
+                                intents.push(CellIntent::Transform { cell: (nx, ny), out: smoke });
+                                return true;
+                            }
+                            false
+                        });
+                    }
+                }
+                return;
+            }
+
+            // Flammable cells ignite once hot enough.
+            let Some(mat) = curr.mat_db.get(id) else { return };
+            if (mat.burn_ticks > 0 && curr.get_temp(x, y) >= mat.ignition_temp) {
+                let fire = fire.unwrap_or_else(|| panic!("Missing material: {}", self.fire_material));
+                intents.push(CellIntent::Ignite { cell: (x, y), fire });
+            }
+        });
+
+        ModuleOutput::CellIntents { intents }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(&self.rng_a, &self.rng_b))?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        (self.rng_a, self.rng_b) = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialDb;
+    use crate::physics::engine::Engine;
+    use crate::physics::module_combustion_heat::ModuleCombustionHeat;
+    use crate::reaction::ReactionDb;
+    use crate::world::World;
+    use std::sync::Arc;
+
+    /// A row of wood with air above, with the given wood temperatures, and an engine running
+    /// combustion and its heat.
+    fn wood_row(temps: &[f32]) -> (World, Engine, Arc<MaterialDb>) {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "base:air": ( phase: gas ),
+                "test:ash": (),
+                "test:smoke": ( phase: gas ),
+                "base:fire": ( burn_ticks: 5, burn_product_name: "base:air" ),
+                "test:wood": (
+                    ignition_temp: 250.0, burn_ticks: 10, burn_heat: 30.0,
+                    burn_product_name: "test:ash", burn_smoke_name: "test:smoke", burn_smoke_chance: 1.0,
+                ),
+            }
+        "#).unwrap();
+        let mat_db = Arc::new(mat_db);
+        let react_db = Arc::new(ReactionDb::new());
+
+        let w = temps.len();
+        let mut world = World::new(w, 2, &mat_db, &react_db);
+        {
+            let (_, mut next) = world.ctx_pair();
+            for (x, &t) in temps.iter().enumerate() {
+                next.set_mat_id(x, 0, mat_db.get_id("base:air").unwrap());
+                next.set_mat_id(x, 1, mat_db.get_id("test:wood").unwrap());
+                next.set_temp(x, 1, t);
+            }
+        }
+        world.swap_all();
+        world.sync_all();
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, w, 2).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleCombustionHeat::new(&curr));
+        engine.add(ModuleCombustion::new(&curr, 42));
+        (world, engine, mat_db)
+    }
+
+    #[test]
+    fn test_wood_burns_to_ash() {
+        let (mut world, mut engine, mat_db) = wood_row(&[300.0, 20.0]);
+        let wood = mat_db.get_id("test:wood").unwrap();
+        let fire = mat_db.get_id("base:fire").unwrap();
+        let ash = mat_db.get_id("test:ash").unwrap();
+        let smoke = mat_db.get_id("test:smoke").unwrap();
+
+        // Hot wood ignites, remembering its fuel. Cold wood does not.
+        engine.step(&mut world);
+        assert_eq!(world.cell_mat_ids.cur[2], fire);
+        assert_eq!(world.cell_fuel.cur[2], wood);
+        assert_eq!(world.cell_mat_ids.cur[3], wood);
+
+        // Fire releases heat every tick and fills the air with smoke.
+        engine.step(&mut world);
+        assert_eq!(world.cell_temps.cur[2], 330.0);
+        assert!(world.cell_mat_ids.cur.contains(&smoke));
+
+        // After burning for the fuel's burn ticks, it leaves ash.
+        for _ in 0..10 {
+            engine.step(&mut world);
+        }
+        assert_eq!(world.cell_mat_ids.cur[2], ash);
+        assert_eq!(world.cell_mat_ids.cur[3], wood);
+    }
+
+    #[test]
+    fn test_painted_fire_uses_own_properties() {
+        let (mut world, mut engine, mat_db) = wood_row(&[20.0]);
+        let fire = mat_db.get_id("base:fire").unwrap();
+        let air = mat_db.get_id("base:air").unwrap();
+        world.cell_mat_ids.cur[0] = fire;
+        world.cell_mat_ids.cur[1] = air;
+
+        // Fire with no flammable fuel burns for its own burn ticks, into its own product.
+        for _ in 0..5 {
+            engine.step(&mut world);
+        }
+        assert_eq!(world.cell_mat_ids.cur[0], fire);
+        engine.step(&mut world);
+        assert_eq!(world.cell_mat_ids.cur[0], air);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::physics::module::{Module, ModuleOutput};
+use crate::physics::module_combustion::{burning_fuel, fire_material_from_config, DEFAULT_FIRE_MATERIAL};
+use crate::world::{CurrCtx, PostRunCtx};
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Releases heat from burning cells: each fire cell gains its fuel's `burn_heat` every tick.
+/// Kept apart from `ModuleCombustion` so the heat is a plain temperature change, which does
+/// not lock fire cells against reactions or other intents.
+pub struct ModuleCombustionHeat {
+    fire_material: String,
+}
+
+impl ModuleCombustionHeat {
+    pub fn new(_curr: &CurrCtx<'_>) -> Self {
+        Self {
+            fire_material: DEFAULT_FIRE_MATERIAL.to_owned(),
+        }
+    }
+}
+
+impl Module for ModuleCombustionHeat {
+
+    fn apply_config(&mut self, config: &HashMap<String, Value>) {
+        self.fire_material = fire_material_from_config(config);
+    }
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+        let w = curr.w;
+        let mut delta_temp = vec![0.0; w * curr.h];
+
+        if let Some(fire) = curr.mat_db.get_id(&self.fire_material) {
+            for (i, &id) in curr.get_mat_ids().iter().enumerate() {
+                if (id != fire) { continue; }
+                if let Some(fuel) = burning_fuel(curr, i % w, i / w, fire) {
+                    delta_temp[i] = fuel.burn_heat;
+                }
+            }
+        }
+
+        ModuleOutput::DeltaTemp { delta_temp }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn load_state(&mut self, _bytes: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
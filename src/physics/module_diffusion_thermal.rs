@@ -2,10 +2,52 @@
 use crate::physics::util::rand_iter_dir;
 use crate::world::{CurrCtx, PostRunCtx};
 use rand::SeedableRng;
+use anyhow::{ensure, Result};
 use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
-use crate::material::{MaterialId};
+use crate::boundary::EdgeMode;
+use crate::material::MaterialId;
+
+/// Largest edge conductance the explicit solver stays stable at, per unit of the lower heat
+/// capacity of the two cells: a cell then gives away at most all of its difference from its
+/// four neighbors.
+const EXPLICIT_MAX_CONDUCTANCE: f32 = 0.25;
+
+/// How the temperature of the next tick is solved for.
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum ThermalSolver {
+    /// Forward Euler: flow across each edge from this tick's temperatures. Fast, but edges are
+    /// capped at `EXPLICIT_MAX_CONDUCTANCE` times the lower heat capacity of their cells.
+    #[default]
+    Explicit,
+    /// Backward Euler: flow across each edge from next tick's temperatures, solved with
+    /// preconditioned conjugate gradient. Stable at any diffusivity, at a cost.
+    Implicit,
+}
+
+/// Thermal settings, read from the `thermal` section of config.
+#[derive(Deserialize, Copy, Clone, Debug)]
+#[serde(default)]
+struct ThermalConfig {
+    solver: ThermalSolver,
+    /// Most conjugate gradient iterations per tick for the implicit solver.
+    max_iterations: u32,
+    /// The implicit solver stops once no cell's temperature is off by more than this.
+    tolerance: f32,
+}
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        Self {
+            solver: ThermalSolver::default(),
+            max_iterations: 100,
+            tolerance: 1e-3,
+        }
+    }
+}
 
 #[inline(always)]
 fn harmonic_mean(a: f32, b: f32) -> f32 {
@@ -13,13 +55,22 @@ fn harmonic_mean(a: f32, b: f32) -> f32 {
     if s == 0.0 { 0.0 } else { (2.0 * a * b) / s }
 }
 
-/// Module for thermal diffusion, using  values to determine heat flow.
+/// Module for thermal diffusion, using diffusivity to determine heat flow across each edge
+/// and heat capacity to turn that heat into a temperature change. Heat leaving one cell
+/// always enters its neighbor, so total heat is conserved across material boundaries. Only
+/// ambient world edges add or remove heat. The solver is set in config: explicit by default,
+/// or implicit so that highly conductive materials such as metals can spread heat across
+/// many cells per tick without oscillating.
 pub struct ModuleDiffusionThermal {
     rng: Xoshiro256PlusPlus,
+    config: ThermalConfig,
 
-    /// Store conductance for every horizontal edge (neighbor pair) in the world.
+    /// Store conductance for every horizontal edge (neighbor pair) in the world, indexed by
+    /// the west cell. The last column holds the edge that wraps around to the first column,
+    /// which is only used when the west and east edges are periodic.
     gx: Vec<f32>,
-    /// Store conductance for every vertical edge (neighbor pair) in the world.
+    /// Store conductance for every vertical edge (neighbor pair) in the world, indexed by the
+    /// north cell. The last row likewise wraps around to the first row.
     gy: Vec<f32>,
 }
 
@@ -30,87 +81,65 @@ impl ModuleDiffusionThermal {
         let mat_ids = curr.get_mat_ids();
         let diff_of = curr.mat_db.get_diffusivity_lookup();
 
-        let mut gx = vec![0.0; (w - 1) * h];
-        let mut gy = vec![0.0; w * (h - 1)];
+        let mut module = Self {
+            rng: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
+            config: ThermalConfig::default(),
+            gx: vec![0.0; w * h],
+            gy: vec![0.0; w * h],
+        };
 
         // Calculate initial state of gx/gy (must check every edge).
-        // Horizontal edges.
         for y in 0..h {
-            for x in 0..(w - 1) {
-                let i0 = y * w + x;
-                let i1 = i0 + 1;
-                let d0 = diff_of[mat_ids[i0].0 as usize];
-                let d1 = diff_of[mat_ids[i1].0 as usize];
-                gx[Self::gx_idx(x, y, w)] = harmonic_mean(d0, d1);
-            }
-        }
-
-        // Vertical edges.
-        for y in 0..h - 1 {
             for x in 0..w {
-                let i0 = y * w + x;
-                let i1 = i0 + w;
-                let d0 = diff_of[mat_ids[i0].0 as usize];
-                let d1 = diff_of[mat_ids[i1].0 as usize];
-                gy[Self::gy_idx(x, y, w)] = harmonic_mean(d0, d1);
+                let i = y * w + x;
+                let d = diff_of[mat_ids[i].0 as usize];
+                let d_e = diff_of[mat_ids[y * w + (x + 1) % w].0 as usize];
+                let d_s = diff_of[mat_ids[((y + 1) % h) * w + x].0 as usize];
+                module.gx[i] = harmonic_mean(d, d_e);
+                module.gy[i] = harmonic_mean(d, d_s);
             }
         }
 
-        Self  {
-            rng: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
-            gx,
-            gy,
-        }
+        module
     }
 
     // Updates conductance for all four edges (between all for neighbors) of the given point.
     pub fn update_conductance_local(&mut self, w: usize, h: usize, i: usize, diff_of: &[f32], future_mat_ids: &[MaterialId]) {
         let (x, y) = (i % w, i / w);
         let d = diff_of[future_mat_ids[i].0 as usize];
+        let (x_w, x_e) = ((x + w - 1) % w, (x + 1) % w);
+        let (y_n, y_s) = ((y + h - 1) % h, (y + 1) % h);
 
         // North edge.
-        if y > 0 {
-            let i_n = i - w;
-            let d_n = diff_of[future_mat_ids[i_n].0 as usize];
-            self.gy[Self::gy_idx(x, y - 1, w)] = harmonic_mean(d, d_n);
-        }
+        let d_n = diff_of[future_mat_ids[y_n * w + x].0 as usize];
+        self.gy[y_n * w + x] = harmonic_mean(d, d_n);
 
         // South edge.
-        if y + 1 < h {
-            let i_s = i + w;
-            let d_s = diff_of[future_mat_ids[i_s].0 as usize];
-            self.gy[Self::gy_idx(x, y, w)] = harmonic_mean(d, d_s);
-        }
+        let d_s = diff_of[future_mat_ids[y_s * w + x].0 as usize];
+        self.gy[i] = harmonic_mean(d, d_s);
 
         // West edge.
-        if x > 0 {
-            let i_w = i - 1;
-            let d_w = diff_of[future_mat_ids[i_w].0 as usize];
-            self.gx[Self::gx_idx(x - 1, y, w)] = harmonic_mean(d, d_w);
-        }
+        let d_w = diff_of[future_mat_ids[y * w + x_w].0 as usize];
+        self.gx[y * w + x_w] = harmonic_mean(d, d_w);
 
         // East edge.
-        if x + 1 < w {
-            let i_e = i + 1;
-            let d_e = diff_of[future_mat_ids[i_e].0 as usize];
-            self.gx[Self::gx_idx(x, y, w)] = harmonic_mean(d, d_e);
-        }
+        let d_e = diff_of[future_mat_ids[y * w + x_e].0 as usize];
+        self.gx[i] = harmonic_mean(d, d_e);
     }
 
-    #[inline(always)]
-    fn gx_idx(x: usize, y: usize, w: usize) -> usize { y * (w - 1) + x }
-    #[inline(always)]
-    fn gy_idx(x: usize, y: usize, w: usize) -> usize { y * w + x}
-}
-
-impl Module for ModuleDiffusionThermal {
-
-    fn apply_config(&mut self, config: &HashMap<String, Value>) {}
-
-    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+    /// Temperature change from one forward Euler step.
+    fn solve_explicit(&mut self, curr: &CurrCtx<'_>) -> Vec<f32> {
         let w = curr.w;
         let h = curr.h;
         let temps = curr.get_temps();
+        let mat_ids = curr.get_mat_ids();
+        let diff_of = curr.mat_db.get_diffusivity_lookup();
+        let cap_of = curr.mat_db.get_heat_capacity_lookup();
+        let boundary = curr.boundary;
+        // Edges more conductive than the scheme can take are capped. The cap is the same from
+        // both sides, so heat is still conserved.
+        let cap = |i: usize| cap_of[mat_ids[i].0 as usize];
+        let g = |g: f32, i: usize, j: usize| g.min(EXPLICIT_MAX_CONDUCTANCE * cap(i).min(cap(j)));
 
         let mut delta_temp = vec![0.0; w * h];
 
@@ -119,35 +148,178 @@ impl Module for ModuleDiffusionThermal {
             let i_loc = y * w + x;
             let t_loc = temps[i_loc];
 
+            // Flux across a world edge. Periodic edges conduct to the cell on the opposite
+            // side, ambient edges to a fixed temperature, at the cell's own diffusivity.
+            let edge_flux = |mode: EdgeMode, g_wrap: f32, i_wrap: usize| {
+                match mode {
+                    EdgeMode::Periodic => g(g_wrap, i_loc, i_wrap) * (temps[i_wrap] - t_loc),
+                    EdgeMode::Ambient => g(diff_of[mat_ids[i_loc].0 as usize], i_loc, i_loc) * (boundary.ambient_temp - t_loc),
+                    EdgeMode::Insulating | EdgeMode::Void => 0.0,
+                }
+            };
+
             let mut flux = 0.0;
 
             // North flux.
             if y > 0 {
-                let g_n = self.gy[Self::gy_idx(x, y - 1, w)];
-                flux += g_n * (temps[i_loc - w] - t_loc);
+                flux += g(self.gy[i_loc - w], i_loc, i_loc - w) * (temps[i_loc - w] - t_loc);
+            } else {
+                let i_wrap = (h - 1) * w + x;
+                flux += edge_flux(boundary.north, self.gy[i_wrap], i_wrap);
             }
 
             // South flux.
             if y + 1 < h {
-                let g_s = self.gy[Self::gy_idx(x, y, w)];
-                flux += g_s * (temps[i_loc + w] - t_loc);
+                flux += g(self.gy[i_loc], i_loc, i_loc + w) * (temps[i_loc + w] - t_loc);
+            } else {
+                flux += edge_flux(boundary.south, self.gy[i_loc], x);
             }
 
             // West flux.
             if x > 0 {
-                let g_w = self.gx[Self::gx_idx(x - 1, y, w)];
-                flux += g_w * (temps[i_loc - 1] - t_loc);
+                flux += g(self.gx[i_loc - 1], i_loc, i_loc - 1) * (temps[i_loc - 1] - t_loc);
+            } else {
+                let i_wrap = y * w + w - 1;
+                flux += edge_flux(boundary.west, self.gx[i_wrap], i_wrap);
             }
 
             // East flux.
             if x + 1 < w {
-                let g_e = self.gx[Self::gx_idx(x, y, w)];
-                flux += g_e * (temps[i_loc + 1] - t_loc);
+                flux += g(self.gx[i_loc], i_loc, i_loc + 1) * (temps[i_loc + 1] - t_loc);
+            } else {
+                flux += edge_flux(boundary.east, self.gx[i_loc], y * w);
             }
 
-            delta_temp[i_loc] += flux;
+            delta_temp[i_loc] += flux / cap(i_loc);
         });
 
+        delta_temp
+    }
+
+    /// Temperature change from one backward Euler step. Solves
+    /// `cap_i * (t_i - t0_i) = sum_j g_ij * (t_j - t_i)` for next tick's temperatures `t`, with
+    /// conjugate gradient preconditioned by the diagonal. The system is symmetric and
+    /// diagonally dominant, so starting from this tick's temperatures it converges quickly.
+    fn solve_implicit(&self, curr: &CurrCtx<'_>) -> Vec<f32> {
+        let w = curr.w;
+        let h = curr.h;
+        let n = w * h;
+        let temps = curr.get_temps();
+        let mat_ids = curr.get_mat_ids();
+        let diff_of = curr.mat_db.get_diffusivity_lookup();
+        let cap_of = curr.mat_db.get_heat_capacity_lookup();
+        let boundary = curr.boundary;
+        let periodic_x = boundary.east == EdgeMode::Periodic;
+        let periodic_y = boundary.south == EdgeMode::Periodic;
+
+        // Conductance of each cell's east and south edge, zero where there is no neighbor, and
+        // the system's diagonal and right-hand side.
+        let mut ge = vec![0.0f64; n];
+        let mut gs = vec![0.0f64; n];
+        let mut diag = vec![0.0f64; n];
+        let mut rhs = vec![0.0f64; n];
+        for y in 0..h {
+            for x in 0..w {
+                let i = y * w + x;
+                let cap = cap_of[mat_ids[i].0 as usize] as f64;
+                diag[i] += cap;
+                rhs[i] += cap * temps[i] as f64;
+
+                if (x + 1 < w || periodic_x) {
+                    let g = self.gx[i] as f64;
+                    ge[i] = g;
+                    diag[i] += g;
+                    diag[y * w + (x + 1) % w] += g;
+                }
+                if (y + 1 < h || periodic_y) {
+                    let g = self.gy[i] as f64;
+                    gs[i] = g;
+                    diag[i] += g;
+                    diag[((y + 1) % h) * w + x] += g;
+                }
+
+                // Ambient edges conduct to a fixed temperature, at the cell's own diffusivity.
+                let ambient_edges = [(x == 0, boundary.west), (x + 1 == w, boundary.east), (y == 0, boundary.north), (y + 1 == h, boundary.south)]
+                    .iter()
+                    .filter(|&&(on_edge, mode)| on_edge && mode == EdgeMode::Ambient)
+                    .count();
+                let g = diff_of[mat_ids[i].0 as usize] as f64 * ambient_edges as f64;
+                diag[i] += g;
+                rhs[i] += g * boundary.ambient_temp as f64;
+            }
+        }
+
+        // out = A * v.
+        let multiply = |v: &[f64], out: &mut [f64]| {
+            for ((o, &d), &v_i) in out.iter_mut().zip(&diag).zip(v) {
+                *o = d * v_i;
+            }
+            for y in 0..h {
+                for x in 0..w {
+                    let i = y * w + x;
+                    if (ge[i] != 0.0) {
+                        let j = y * w + (x + 1) % w;
+                        out[i] -= ge[i] * v[j];
+                        out[j] -= ge[i] * v[i];
+                    }
+                    if (gs[i] != 0.0) {
+                        let j = ((y + 1) % h) * w + x;
+                        out[i] -= gs[i] * v[j];
+                        out[j] -= gs[i] * v[i];
+                    }
+                }
+            }
+        };
+        let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+
+        let mut t: Vec<f64> = temps.iter().map(|&t| t as f64).collect();
+        let mut q = vec![0.0f64; n];
+        multiply(&t, &mut q);
+        let mut r: Vec<f64> = rhs.iter().zip(&q).map(|(b, q)| b - q).collect();
+        let mut z: Vec<f64> = r.iter().zip(&diag).map(|(r, d)| r / d).collect();
+        let mut p = z.clone();
+        let mut rz = dot(&r, &z);
+
+        for _ in 0..self.config.max_iterations {
+            // Dividing by the diagonal turns each cell's residual heat into degrees.
+            if (z.iter().all(|z| z.abs() <= self.config.tolerance as f64)) { break; }
+
+            multiply(&p, &mut q);
+            let pq = dot(&p, &q);
+            if (pq <= 0.0) { break; }
+            let alpha = rz / pq;
+            for (((t, r), &p), &q) in t.iter_mut().zip(r.iter_mut()).zip(&p).zip(&q) {
+                *t += alpha * p;
+                *r -= alpha * q;
+            }
+            for ((z, r), d) in z.iter_mut().zip(&r).zip(&diag) {
+                *z = r / d;
+            }
+            let rz_next = dot(&r, &z);
+            let beta = rz_next / rz;
+            rz = rz_next;
+            for (p, &z) in p.iter_mut().zip(&z) {
+                *p = z + beta * *p;
+            }
+        }
+
+        t.iter().zip(temps).map(|(&t, &t0)| (t - t0 as f64) as f32).collect()
+    }
+}
+
+impl Module for ModuleDiffusionThermal {
+
+    fn apply_config(&mut self, config: &HashMap<String, Value>) {
+        if let Some(v) = config.get("thermal") {
+            self.config = ThermalConfig::deserialize(v).expect("Invalid config: thermal");
+        }
+    }
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+        let delta_temp = match self.config.solver {
+            ThermalSolver::Explicit => self.solve_explicit(curr),
+            ThermalSolver::Implicit => self.solve_implicit(curr),
+        };
         ModuleOutput::DeltaTemp { delta_temp }
     }
 
@@ -160,4 +332,258 @@ impl Module for ModuleDiffusionThermal {
             self.update_conductance_local(w, h, i, diff_of, mat_ids);
         }
     }
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(&self.rng, &self.gx, &self.gy))?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let (rng, gx, gy): (Xoshiro256PlusPlus, Vec<f32>, Vec<f32>) = bincode::deserialize(bytes)?;
+        ensure!(gx.len() == self.gx.len() && gy.len() == self.gy.len(), "conductance buffers do not match world size");
+        (self.rng, self.gx, self.gy) = (rng, gx, gy);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Boundary;
+    use crate::material::MaterialDb;
+    use crate::physics::engine::Engine;
+    use crate::reaction::ReactionDb;
+    use crate::world::World;
+    use std::sync::Arc;
+
+    /// Total heat in the world: each cell's temperature weighted by its heat capacity.
+    fn total_heat(world: &World) -> f64 {
+        world.cell_mat_ids.cur.iter().zip(&world.cell_temps.cur)
+            .map(|(&id, &t)| world.mat_db.heat_capacity_of(id) as f64 * t as f64)
+            .sum()
+    }
+
+    #[test]
+    fn test_energy_conserved_in_insulated_box() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "base:air": ( diffusivity: 0.05 ),
+                "test:copper": ( diffusivity: 0.2, heat_capacity: 3.5 ),
+                "test:water": ( diffusivity: 0.01, heat_capacity: 4.0 ),
+            }
+        "#).unwrap();
+        let mat_db = Arc::new(mat_db);
+        let react_db = Arc::new(ReactionDb::new());
+        let mats = ["base:air", "test:copper", "test:water"].map(|n| mat_db.get_id(n).unwrap());
+
+        for solver in ["explicit", "implicit"] {
+            // World edges are insulating, so the world itself is a closed box.
+            let (w, h) = (8, 8);
+            let mut world = World::new(w, h, &mat_db, &react_db);
+            {
+                let (_, mut next) = world.ctx_pair();
+                for y in 0..h {
+                    for x in 0..w {
+                        next.set_mat_id(x, y, mats[(x * 3 + y) % 3]);
+                        next.set_temp(x, y, ((x * 37 + y * 11) % 500) as f32);
+                    }
+                }
+            }
+            world.swap_all();
+            world.sync_all();
+
+            let config = ron::de::from_str(&format!(r#"{{ "thermal": {{ "solver": "{}" }} }}"#, solver)).unwrap();
+            let mut engine = Engine::new(config, &world.mat_db, w, h).unwrap();
+            let (curr, _) = world.ctx_pair();
+            engine.add(ModuleDiffusionThermal::new(&curr, 42));
+
+            let before = total_heat(&world);
+            for _ in 0..500 {
+                engine.step(&mut world);
+            }
+            let after = total_heat(&world);
+            assert!((after - before).abs() < before * 1e-4, "{}: heat before {}, after {}", solver, before, after);
+        }
+    }
+
+    #[test]
+    fn test_heat_capacity_slows_temperature_change() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "base:air": (),
+                "test:rock": ( diffusivity: 0.1 ),
+                "test:water": ( diffusivity: 0.1, heat_capacity: 4.0 ),
+            }
+        "#).unwrap();
+        let mat_db = Arc::new(mat_db);
+        let react_db = Arc::new(ReactionDb::new());
+
+        // Hot rock next to cold water.
+        let mut world = World::new(2, 1, &mat_db, &react_db);
+        {
+            let (_, mut next) = world.ctx_pair();
+            next.set_mat_id(0, 0, mat_db.get_id("test:rock").unwrap());
+            next.set_temp(0, 0, 100.0);
+            next.set_mat_id(1, 0, mat_db.get_id("test:water").unwrap());
+        }
+        world.swap_all();
+        world.sync_all();
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 1).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleDiffusionThermal::new(&curr, 42));
+        engine.step(&mut world);
+
+        // Ten degrees of heat flow: the rock loses all ten, the water gains a quarter of it.
+        assert!((world.cell_temps.cur[0] - 90.0).abs() < 1e-4);
+        assert!((world.cell_temps.cur[1] - 2.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_low_heat_capacity_conserves_heat() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "test:rock": ( diffusivity: 0.1 ),
+                "base:air": ( diffusivity: 0.1, heat_capacity: 0.25 ),
+            }
+        "#).unwrap();
+        let mat_db = Arc::new(mat_db);
+        let react_db = Arc::new(ReactionDb::new());
+
+        for solver in ["explicit", "implicit"] {
+            // Hot rock next to cold air.
+            let mut world = World::new(2, 1, &mat_db, &react_db);
+            {
+                let (_, mut next) = world.ctx_pair();
+                next.set_mat_id(0, 0, mat_db.get_id("test:rock").unwrap());
+                next.set_temp(0, 0, 100.0);
+                next.set_mat_id(1, 0, mat_db.get_id("base:air").unwrap());
+            }
+            world.swap_all();
+            world.sync_all();
+
+            let config = ron::de::from_str(&format!(r#"{{ "thermal": {{ "solver": "{}" }} }}"#, solver)).unwrap();
+            let mut engine = Engine::new(config, &world.mat_db, 2, 1).unwrap();
+            let (curr, _) = world.ctx_pair();
+            engine.add(ModuleDiffusionThermal::new(&curr, 42));
+            engine.step(&mut world);
+
+            // The light air warms faster than the rock cools, and heat is conserved.
+            let temps = &world.cell_temps.cur;
+            assert!(temps[1] > 100.0 - temps[0], "{}: temps: {:?}", solver, temps);
+            assert!((temps[0] + 0.25 * temps[1] - 100.0).abs() < 1e-2, "{}: temps: {:?}", solver, temps);
+        }
+    }
+
+    /// A row of rock cells at the given temperatures, with an engine running only diffusion.
+    fn rock_row(temps: &[f32], boundary: Boundary) -> (World, Engine) {
+        material_row(0.1, temps, boundary, "explicit")
+    }
+
+    /// A row of cells of a material with the given diffusivity, at the given temperatures,
+    /// with an engine running only diffusion with the given solver.
+    fn material_row(diffusivity: f32, temps: &[f32], boundary: Boundary, solver: &str) -> (World, Engine) {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(&format!(r#"{{ "base:air": (), "test:rock": ( diffusivity: {} ) }}"#, diffusivity)).unwrap();
+        let mat_db = Arc::new(mat_db);
+        let react_db = Arc::new(ReactionDb::new());
+
+        let mut world = World::new(temps.len(), 1, &mat_db, &react_db);
+        world.boundary = boundary;
+        {
+            let (_, mut next) = world.ctx_pair();
+            for (x, &t) in temps.iter().enumerate() {
+                next.set_mat_id(x, 0, mat_db.get_id("test:rock").unwrap());
+                next.set_temp(x, 0, t);
+            }
+        }
+        world.swap_all();
+        world.sync_all();
+
+        let config = ron::de::from_str(&format!(r#"{{ "thermal": {{ "solver": "{}" }} }}"#, solver)).unwrap();
+        let mut engine = Engine::new(config, &world.mat_db, temps.len(), 1).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleDiffusionThermal::new(&curr, 42));
+        (world, engine)
+    }
+
+    #[test]
+    fn test_ambient_edge_sinks_heat() {
+        let boundary = Boundary { east: EdgeMode::Ambient, ambient_temp: 20.0, ..Boundary::default() };
+        let (mut world, mut engine) = rock_row(&[500.0, 500.0, 500.0], boundary);
+
+        // Only the cell on the ambient edge loses heat at first.
+        engine.step(&mut world);
+        assert_eq!(world.cell_temps.cur[0], 500.0);
+        assert!(world.cell_temps.cur[2] < 500.0);
+
+        // Eventually the whole row cools to ambient.
+        for _ in 0..2000 {
+            engine.step(&mut world);
+        }
+        assert!(world.cell_temps.cur.iter().all(|&t| (t - 20.0).abs() < 0.1), "temps: {:?}", world.cell_temps.cur);
+    }
+
+    #[test]
+    fn test_periodic_edge_wraps_heat() {
+        let boundary = Boundary { west: EdgeMode::Periodic, east: EdgeMode::Periodic, ..Boundary::default() };
+        let (mut world, mut engine) = rock_row(&[100.0, 0.0, 0.0, 0.0], boundary);
+        engine.step(&mut world);
+
+        // Heat flows both ways out of the hot cell, including across the west edge.
+        let temps = &world.cell_temps.cur;
+        assert!((temps[0] - 80.0).abs() < 1e-4);
+        assert!((temps[1] - 10.0).abs() < 1e-4);
+        assert_eq!(temps[2], 0.0);
+        assert!((temps[3] - 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_implicit_solver_is_stable_at_high_diffusivity() {
+        let mut temps = [0.0; 8];
+        temps[0] = 1000.0;
+
+        // Explicitly, the edges are capped, so heat only reaches the next cell in one tick.
+        let (mut world, mut engine) = material_row(20.0, &temps, Boundary::default(), "explicit");
+        engine.step(&mut world);
+        assert!((world.cell_temps.cur[0] - 750.0).abs() < 1e-3);
+        assert_eq!(world.cell_temps.cur[2], 0.0);
+
+        // Implicitly, heat spreads down the whole row in one tick, without overshooting or
+        // oscillating, and heat is conserved.
+        let (mut world, mut engine) = material_row(20.0, &temps, Boundary::default(), "implicit");
+        engine.step(&mut world);
+        let temps = &world.cell_temps.cur;
+        assert!(temps[7] > 10.0, "temps: {:?}", temps);
+        assert!(temps.windows(2).all(|p| p[0] >= p[1] && p[1] >= 0.0), "temps: {:?}", temps);
+        assert!((temps.iter().sum::<f32>() - 1000.0).abs() < 0.1);
+
+        // And soon evens out.
+        for _ in 0..20 {
+            engine.step(&mut world);
+        }
+        assert!(world.cell_temps.cur.iter().all(|&t| (t - 125.0).abs() < 0.5), "temps: {:?}", world.cell_temps.cur);
+    }
+
+    #[test]
+    fn test_implicit_solver_edges() {
+        // Ambient edges sink heat.
+        let boundary = Boundary { east: EdgeMode::Ambient, ambient_temp: 20.0, ..Boundary::default() };
+        let (mut world, mut engine) = material_row(5.0, &[500.0, 500.0, 500.0], boundary, "implicit");
+        for _ in 0..200 {
+            engine.step(&mut world);
+        }
+        assert!(world.cell_temps.cur.iter().all(|&t| (t - 20.0).abs() < 0.1), "temps: {:?}", world.cell_temps.cur);
+
+        // Periodic edges conduct across the world, both ways alike.
+        let boundary = Boundary { west: EdgeMode::Periodic, east: EdgeMode::Periodic, ..Boundary::default() };
+        let (mut world, mut engine) = material_row(0.1, &[100.0, 0.0, 0.0, 0.0, 0.0], boundary, "implicit");
+        engine.step(&mut world);
+        let temps = &world.cell_temps.cur;
+        assert!(temps[4] > 0.0);
+        assert!((temps[1] - temps[4]).abs() < 1e-3);
+        assert!((temps[2] - temps[3]).abs() < 1e-3);
+    }
 }
@@ -0,0 +1,162 @@
+use crate::material::MaterialId;
+use crate::physics::intent::CellIntent;
+use crate::physics::module::{Module, ModuleOutput};
+use crate::physics::util::rand_iter_dir;
+use crate::world::{CurrCtx, PostRunCtx};
+use rand::SeedableRng;
+use anyhow::Result;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Detonates explosives that reach their `blast_temp`. Anything that heats an explosive can
+/// set it off: fire, another blast, or a reaction with heat. Each detonation is a single
+/// `Blast` intent, so it resolves atomically: if any cell it would touch has already changed
+/// this tick, the whole blast waits for a later tick. The explosive is always consumed by its
+/// own blast, so it detonates only once.
+pub struct ModuleExplosion {
+    rng: Xoshiro256PlusPlus,
+    /// Material blasted cells are left as, usually `Engine::empty_material`.
+    empty_material: MaterialId,
+}
+
+impl ModuleExplosion {
+    pub fn new(_curr: &CurrCtx<'_>, empty_material: MaterialId, rng_seed: u64) -> Self {
+        Self {
+            rng: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
+            empty_material,
+        }
+    }
+}
+
+impl Module for ModuleExplosion {
+
+    fn apply_config(&mut self, _config: &HashMap<String, Value>) {}
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+
+        let mut intents = vec![];
+
+        rand_iter_dir(&mut self.rng, curr.w, curr.h, |x, y| {
+            let Some(mat) = curr.mat_db.get(curr.get_mat_id(x, y)) else { return };
+            if (mat.blast_radius == 0 || curr.get_temp(x, y) < mat.blast_temp) { return; }
+
+            intents.push(CellIntent::Blast {
+                center: (x, y),
+                radius: mat.blast_radius,
+                power: mat.blast_power,
+                heat: mat.blast_heat,
+                fill: self.empty_material,
+            });
+        });
+
+        ModuleOutput::CellIntents { intents }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&self.rng)?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        self.rng = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialDb;
+    use crate::physics::command::{Region, WorldCommand};
+    use crate::physics::engine::Engine;
+    use crate::physics::util::{mat_name, row_string, world_from_rows};
+    use crate::world::World;
+    use std::sync::Arc;
+
+    /// Build a world from rows of '.' (air), 'x' (explosive), 'g' (gas explosive), 'h' (hard
+    /// explosive), 'd' (dirt) and '#' (rock), with an engine running only explosions.
+    fn blast_world(rows: &[&str], explosive_temp: f32) -> (World, Engine, Arc<MaterialDb>) {
+        let (mut world, mat_db) = world_from_rows(rows, r#"
+            {
+                "base:air": ( phase: gas ),
+                "test:dirt": ( phase: powder, hardness: 1.0 ),
+                "test:rock": ( hardness: 50.0 ),
+                "test:tnt": ( blast_temp: 200.0, blast_radius: 2, blast_power: 10.0, blast_heat: 300.0 ),
+                "test:gas_bomb": ( phase: gas, blast_temp: 100.0, blast_radius: 1, blast_power: 5.0, blast_heat: 100.0 ),
+                "test:hard_bomb": ( hardness: 100.0, blast_temp: 100.0, blast_radius: 1, blast_power: 5.0, blast_heat: 100.0 ),
+            }
+        "#, |c| (mat_name(c), if (matches!(c, 'x' | 'g' | 'h')) { explosive_temp } else { 0.0 }));
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, world.w, world.h).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleExplosion::new(&curr, engine.empty_material(), 42));
+        (world, engine, mat_db)
+    }
+
+    #[test]
+    fn test_blast_destroys_soft_and_flings_debris() {
+        let rows = [
+            ".......",
+            ".......",
+            "..dxd..",
+            "..###..",
+            ".......",
+        ];
+
+        // Cold explosive does nothing.
+        let (mut world, mut engine, mat_db) = blast_world(&rows, 20.0);
+        engine.step(&mut world);
+        assert_eq!(row_string(&world, &mat_db, 2), "..dxd..");
+
+        // Hot explosive blows itself and the dirt apart. The dirt lands on the rim, and the
+        // stone survives, heated.
+        let (mut world, mut engine, mat_db) = blast_world(&rows, 250.0);
+        engine.step(&mut world);
+        assert_eq!(row_string(&world, &mat_db, 2), "d.....d");
+        assert_eq!(row_string(&world, &mat_db, 3), "..###..");
+        assert!((world.cell_temps.cur[3 * 7 + 3] - 200.0).abs() < 1e-3); // One cell from center: 300 * (1 - 1/3).
+    }
+
+    #[test]
+    fn test_blast_consumes_gas_and_hard_explosives() {
+        let (mut world, mut engine, mat_db) = blast_world(&[
+            ".........",
+            ".g.....h.",
+            ".........",
+        ], 150.0);
+
+        // Neither a gas nor one harder than its own blast survives detonating, so each goes
+        // off once.
+        engine.step(&mut world);
+        assert_eq!(row_string(&world, &mat_db, 1), ".........");
+        let temp = world.cell_temps.cur[9 + 1];
+        for _ in 0..10 {
+            engine.step(&mut world);
+        }
+        assert_eq!(world.cell_temps.cur[9 + 1], temp);
+    }
+
+    #[test]
+    fn test_blast_is_atomic() {
+        let (mut world, mut engine, mat_db) = blast_world(&[
+            ".......",
+            "...x...",
+            ".......",
+        ], 250.0);
+
+        // A command changes a cell on the blast's rim first, so the blast waits a tick.
+        let dirt = mat_db.get_id("test:dirt").unwrap();
+        engine.queue_command(WorldCommand::PlaceMaterial {
+            region: Region::Cell { x: 0, y: 1 },
+            mat: dirt,
+            temp: None,
+        });
+        engine.step(&mut world);
+        assert_eq!(row_string(&world, &mat_db, 1), "d..x...");
+
+        engine.step(&mut world);
+        assert_eq!(row_string(&world, &mat_db, 1), "d......");
+    }
+}
@@ -0,0 +1,610 @@
+use crate::boundary::EdgeMode;
+use crate::material::Phase;
+use crate::physics::module::{Module, ModuleOutput};
+use crate::physics::util::NEIGHBORS_4;
+use crate::world::{CurrCtx, PostRunCtx, KELVIN};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Fastest a fluid can move, in cells per tick. Kept below one so advection only ever looks
+/// at direct neighbors, and so `ModuleFluidFlow` can move a cell at most one step per tick.
+pub const MAX_SPEED: f32 = 0.9;
+
+/// Over-relaxation factor for the pressure solve. Converges much faster than plain
+/// Gauss-Seidel for the same number of iterations.
+const SOR_OMEGA: f32 = 1.7;
+
+/// Upward acceleration, in cells per tick per tick, of fluid far hotter than the fluid around
+/// it at a convection strength of 1.0.
+const BUOYANCY_ACCEL: f32 = 0.02;
+
+/// Half-width, in cells, of the square around a cell whose same-phase fluid sets the ambient
+/// temperature its buoyancy is measured against.
+const AMBIENT_RADIUS: usize = 4;
+
+/// Fluid settings, read from the `fluid` section of config.
+#[derive(Deserialize, Copy, Clone, Debug)]
+#[serde(default)]
+pub(crate) struct FluidConfig {
+    /// Velocity that gases are dragged toward, in cells per tick. Liquids are dragged toward rest.
+    pub wind_x: f32,
+    pub wind_y: f32,
+    /// Fraction of the gap to the drag target closed per tick.
+    pub drag: f32,
+    /// Strength of convection: how hard hot fluid is pushed up and cold fluid down. 0.0 turns
+    /// convection off, leaving heat to move by conduction alone.
+    pub convection: f32,
+    /// Gauss-Seidel iterations per tick when projecting out divergence.
+    pub iterations: u32,
+}
+
+impl Default for FluidConfig {
+    fn default() -> Self {
+        Self {
+            wind_x: 0.0,
+            wind_y: 0.0,
+            drag: 0.02,
+            convection: 1.0,
+            iterations: 30,
+        }
+    }
+}
+
+/// Solves the fluid velocity field of every liquid and gas cell, as a single incompressible
+/// fluid on a staggered grid ("stable fluids"). Velocities live on cell faces (see
+/// `World::cell_vel_x`). Each tick it drags gases toward the wind and liquids toward rest,
+/// pushes fluid hotter than the nearby fluid of its phase up and colder fluid down
+/// (convection, so heat is carried by the flow as well as conducted), advects velocity along
+/// itself semi-Lagrangian style, and projects out divergence with over-relaxed Gauss-Seidel,
+/// which turns pushes into currents and vortices around obstacles. The solve starts from last
+/// tick's pressure, so it keeps converging across ticks.
+/// Faces touching solids, powders or world edges are closed. Velocity can also be added with
+/// `WorldCommand::AddVelocity`. Cells are moved along the field by `ModuleFluidFlow`.
+/// Compression is left to `ModulePressure`.
+pub struct ModuleFluid {
+    config: FluidConfig,
+    /// Pressure solved last tick, the starting guess for this tick's solve.
+    pressure: Vec<f32>,
+    scratch: Scratch,
+}
+
+/// Working buffers for one tick of `ModuleFluid`, kept between ticks so they are not
+/// reallocated. Only entries for fluid cells are meaningful.
+#[derive(Default)]
+struct Scratch {
+    /// Phase of every cell.
+    phases: Vec<Phase>,
+    /// Fluid cells, in index order.
+    cells: Vec<u32>,
+    /// Fluid cells with at least one open face, split by checkerboard color for the
+    /// red-black pressure solve.
+    red: Vec<u32>,
+    black: Vec<u32>,
+    /// The fluid cell across each face, north, west, east and south. Closed faces, which
+    /// touch a wall, a void edge or a non-fluid cell, link to one past the last cell instead.
+    links: Vec<[u32; 4]>,
+    /// One over the number of open faces.
+    inv_count: Vec<f32>,
+    /// Temperatures of liquid and gas cells summed along each row, within `AMBIENT_RADIUS`,
+    /// with the number of cells summed. Summed in f64, so sliding the window does not build
+    /// up rounding error across wide worlds.
+    row_sums: [Vec<f64>; 4],
+    /// The row sums summed down each column, for one row at a time.
+    window_sums: [Vec<f64>; 4],
+    uc: Vec<f32>,
+    vc: Vec<f32>,
+    u_adv: Vec<f32>,
+    v_adv: Vec<f32>,
+    u: Vec<f32>,
+    v: Vec<f32>,
+    div: Vec<f32>,
+}
+
+impl FluidConfig {
+    pub(crate) fn from_config(config: &HashMap<String, Value>) -> Self {
+        match config.get("fluid") {
+            Some(v) => FluidConfig::deserialize(v).expect("Invalid config: fluid"),
+            None => FluidConfig::default(),
+        }
+    }
+}
+
+impl ModuleFluid {
+    pub fn new(_curr: &CurrCtx<'_>) -> Self {
+        Self {
+            config: FluidConfig::default(),
+            pressure: vec![],
+            scratch: Scratch::default(),
+        }
+    }
+}
+
+#[inline]
+fn is_fluid(phase: Phase) -> bool {
+    matches!(phase, Phase::Liquid | Phase::Gas)
+}
+
+/// Index of the cell at (x + dx, y + dy), for offsets of at most one cell, wrapping around
+/// periodic edges. None past walls and voids.
+#[inline]
+fn offset_cell(curr: &CurrCtx<'_>, x: usize, y: usize, dx: isize, dy: isize) -> Option<usize> {
+    if (x > 0 && x + 1 < curr.w && y > 0 && y + 1 < curr.h) {
+        Some((y as isize + dy) as usize * curr.w + (x as isize + dx) as usize)
+    } else {
+        curr.neighbor_cell(x, y, dx, dy).map(|(nx, ny)| ny * curr.w + nx)
+    }
+}
+
+/// The fluid cells around (x + ox, y + oy) and the weights to bilinearly sample them with,
+/// for offsets of less than one cell. Cells past walls or outside the fluid are `None`, and
+/// sample as zero.
+#[inline]
+fn bilinear(curr: &CurrCtx<'_>, phases: &[Phase], x: usize, y: usize, ox: f32, oy: f32) -> ([Option<usize>; 4], f32, f32) {
+    // Offsets are under one cell, so they floor to -1 or 0.
+    let floor = |o: f32| if (o < 0.0) { -1.0 } else { 0.0 };
+    let (fx, fy) = (floor(ox), floor(oy));
+    let (dx, dy) = (fx as isize, fy as isize);
+    let at = |dx, dy| offset_cell(curr, x, y, dx, dy).filter(|&j| is_fluid(phases[j]));
+    ([at(dx, dy), at(dx + 1, dy), at(dx, dy + 1), at(dx + 1, dy + 1)], ox - fx, oy - fy)
+}
+
+/// Sample `field` at the cells and weights found by `bilinear`.
+#[inline]
+fn sample_bilinear(field: &[f32], (cells, tx, ty): ([Option<usize>; 4], f32, f32)) -> f32 {
+    let at = |k: usize| cells[k].map_or(0.0, |j| field[j]);
+    let top = at(0) * (1.0 - tx) + at(1) * tx;
+    let bottom = at(2) * (1.0 - tx) + at(3) * tx;
+    top * (1.0 - ty) + bottom * ty
+}
+
+/// How strongly fluid at `temp` is lifted within surrounding fluid at `ambient`, from -1.0
+/// (far colder) to 1.0 (far hotter). Follows the density change of an ideal gas near ambient,
+/// but stays bounded for extreme differences such as lava.
+#[inline]
+pub(crate) fn buoyancy(temp: f32, ambient: f32) -> f32 {
+    let t = (temp + KELVIN).max(1.0);
+    let t_ambient = (ambient + KELVIN).max(1.0);
+    (t - t_ambient) / (t + t_ambient)
+}
+
+impl Scratch {
+    /// Size the buffers for a world of `n` cells, and find its fluid cells and open faces.
+    fn prepare(&mut self, curr: &CurrCtx<'_>) {
+        let (w, h) = (curr.w, curr.h);
+        let n = w * h;
+        if (self.phases.len() != n) {
+            *self = Scratch {
+                phases: vec![Phase::Solid; n],
+                links: vec![[n as u32; 4]; n],
+                inv_count: vec![0.0; n],
+                row_sums: std::array::from_fn(|_| vec![0.0; n]),
+                window_sums: std::array::from_fn(|_| vec![0.0; w]),
+                uc: vec![0.0; n],
+                vc: vec![0.0; n],
+                u_adv: vec![0.0; n],
+                v_adv: vec![0.0; n],
+                u: vec![0.0; n],
+                v: vec![0.0; n],
+                div: vec![0.0; n],
+                ..Scratch::default()
+            };
+        }
+
+        let phase_of = curr.mat_db.get_phase_lookup();
+        for (phase, id) in self.phases.iter_mut().zip(curr.get_mat_ids()) {
+            *phase = phase_of[id.0 as usize];
+        }
+
+        self.cells.clear();
+        self.red.clear();
+        self.black.clear();
+        for y in 0..h {
+            for x in 0..w {
+                let i = y * w + x;
+                if (!is_fluid(self.phases[i])) { continue; }
+                self.cells.push(i as u32);
+                let mut count = 0;
+                for (link, (dx, dy)) in self.links[i].iter_mut().zip(NEIGHBORS_4) {
+                    *link = match offset_cell(curr, x, y, dx, dy) {
+                        Some(j) if is_fluid(self.phases[j]) => {
+                            count += 1;
+                            j as u32
+                        }
+                        _ => n as u32,
+                    };
+                }
+                self.inv_count[i] = if (count == 0) { 0.0 } else { 1.0 / count as f32 };
+                if (count > 0) {
+                    if ((x + y) % 2 == 0) { self.red.push(i as u32) } else { self.black.push(i as u32) }
+                }
+            }
+        }
+    }
+
+    /// Push each fluid cell up by `accel` times its buoyancy against the mean temperature of
+    /// the fluid of its phase within `AMBIENT_RADIUS`. Cells with none nearby are left alone.
+    /// Buoyancy is measured against this rather than a fixed reference, so a hot liquid is not
+    /// lifted by the colder gas above it.
+    fn add_buoyancy(&mut self, curr: &CurrCtx<'_>, accel: f32) {
+        let (w, h) = (curr.w, curr.h);
+        let temps = curr.get_temps();
+
+        // Sums along each row, sliding the window one cell at a time.
+        let [liquid_sum, liquid_count, gas_sum, gas_count] = &mut self.row_sums;
+        for y in 0..h {
+            let row = y * w;
+            let add = |sums: &mut [f64; 4], x: usize, sign: f64| {
+                let liquid = sign * (self.phases[row + x] == Phase::Liquid) as u8 as f64;
+                let gas = sign * (self.phases[row + x] == Phase::Gas) as u8 as f64;
+                let t = temps[row + x] as f64;
+                sums[0] += liquid * t;
+                sums[1] += liquid;
+                sums[2] += gas * t;
+                sums[3] += gas;
+            };
+            slide_window(&mut [0.0; 4], w, curr.boundary.west == EdgeMode::Periodic, add, |sums, x| {
+                liquid_sum[row + x] = sums[0];
+                liquid_count[row + x] = sums[1];
+                gas_sum[row + x] = sums[2];
+                gas_count[row + x] = sums[3];
+            });
+        }
+
+        // Then down each column, sliding the window one row at a time.
+        for window in &mut self.window_sums {
+            window.fill(0.0);
+        }
+        let add = |windows: &mut [Vec<f64>; 4], y: usize, sign: f64| {
+            for (window, row_sum) in windows.iter_mut().zip(&self.row_sums) {
+                for (a, &b) in window.iter_mut().zip(&row_sum[y * w..(y + 1) * w]) {
+                    *a += sign * b;
+                }
+            }
+        };
+        let (phases, vc) = (&self.phases, &mut self.vc);
+        slide_window(&mut self.window_sums, h, curr.boundary.north == EdgeMode::Periodic, add, |windows, y| {
+            for x in 0..w {
+                let k = match phases[y * w + x] {
+                    Phase::Liquid => 0,
+                    Phase::Gas => 2,
+                    _ => continue,
+                };
+                let count = windows[k + 1][x];
+                if (count > 0.5) {
+                    let i = y * w + x;
+                    vc[i] -= accel * buoyancy(temps[i], (windows[k][x] / count) as f32);
+                }
+            }
+        });
+    }
+}
+
+/// Slide a window of `AMBIENT_RADIUS` cells either side along a line of `len` cells. Calls
+/// `add` with 1.0 as each cell enters the window and -1.0 as it leaves, and `emit` once the
+/// window is centered on each cell in turn. Windows wrap around periodic lines, and are
+/// clipped at the ends of others.
+fn slide_window<S: ?Sized>(state: &mut S, len: usize, periodic: bool, add: impl Fn(&mut S, usize, f64), mut emit: impl FnMut(&mut S, usize)) {
+    let r = AMBIENT_RADIUS;
+    if (periodic && 2 * r + 1 >= len) {
+        // The window wraps onto itself, so it always covers the whole line.
+        for j in 0..len {
+            add(state, j, 1.0);
+        }
+        for i in 0..len {
+            emit(state, i);
+        }
+    } else if (periodic) {
+        for j in (len - r..len).chain(0..r) {
+            add(state, j, 1.0);
+        }
+        for i in 0..len {
+            add(state, (i + r) % len, 1.0);
+            if (i > 0) { add(state, (i + len - r - 1) % len, -1.0); }
+            emit(state, i);
+        }
+    } else {
+        for j in 0..r.min(len) {
+            add(state, j, 1.0);
+        }
+        for i in 0..len {
+            if (i + r < len) { add(state, i + r, 1.0); }
+            if (i > r) { add(state, i - r - 1, -1.0); }
+            emit(state, i);
+        }
+    }
+}
+
+impl Module for ModuleFluid {
+
+    fn apply_config(&mut self, config: &HashMap<String, Value>) {
+        self.config = FluidConfig::from_config(config);
+    }
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+        let w = curr.w;
+        let n = w * curr.h;
+        let cfg = self.config;
+        let s = &mut self.scratch;
+        s.prepare(curr);
+        let face = |field: &[f32], j: u32| if (j as usize == n) { 0.0 } else { field[j as usize] };
+
+        // Cell-centered velocity, averaged from the faces.
+        for &i in &s.cells {
+            let i = i as usize;
+            let [north, west, east, south] = s.links[i];
+            let own = |field: &[f32], j: u32| if (j as usize == n) { 0.0 } else { field[i] };
+            s.uc[i] = 0.5 * (face(curr.cell_vel_x, west) + own(curr.cell_vel_x, east));
+            s.vc[i] = 0.5 * (face(curr.cell_vel_y, north) + own(curr.cell_vel_y, south));
+        }
+
+        // Forces. Buoyancy from temperature against the surrounding fluid of the same phase,
+        // then gases are dragged toward the wind, and liquids toward rest.
+        if (cfg.convection != 0.0) {
+            s.add_buoyancy(curr, cfg.convection * BUOYANCY_ACCEL);
+        }
+        for &i in &s.cells {
+            let i = i as usize;
+            let (target_x, target_y) = if (s.phases[i] == Phase::Gas) {
+                (cfg.wind_x, cfg.wind_y)
+            } else {
+                (0.0, 0.0)
+            };
+            s.uc[i] = (s.uc[i] + cfg.drag * (target_x - s.uc[i])).clamp(-MAX_SPEED, MAX_SPEED);
+            s.vc[i] = (s.vc[i] + cfg.drag * (target_y - s.vc[i])).clamp(-MAX_SPEED, MAX_SPEED);
+        }
+
+        // Advection. Each cell takes the velocity found where its fluid came from last tick.
+        for y in 0..curr.h {
+            for x in 0..w {
+                let i = y * w + x;
+                if (!is_fluid(s.phases[i])) { continue; }
+                let from = bilinear(curr, &s.phases, x, y, -s.uc[i], -s.vc[i]);
+                s.u_adv[i] = sample_bilinear(&s.uc, from);
+                s.v_adv[i] = sample_bilinear(&s.vc, from);
+            }
+        }
+
+        // Back onto the faces. Closed faces are at rest.
+        s.u.fill(0.0);
+        s.v.fill(0.0);
+        for &i in &s.cells {
+            let i = i as usize;
+            let [_, _, east, south] = s.links[i];
+            if (east as usize != n) { s.u[i] = 0.5 * (s.u_adv[i] + s.u_adv[east as usize]); }
+            if (south as usize != n) { s.v[i] = 0.5 * (s.v_adv[i] + s.v_adv[south as usize]); }
+        }
+
+        // Projection. Solve for the pressure whose gradient cancels the net flow out of every
+        // cell, then subtract its gradient across each open face. Pressure has one extra
+        // entry, always zero, for closed faces to read.
+        for &i in &s.cells {
+            let i = i as usize;
+            let [north, west, _, _] = s.links[i];
+            s.div[i] = s.u[i] - face(&s.u, west) + s.v[i] - face(&s.v, north);
+        }
+        let p = &mut self.pressure;
+        p.resize(n + 1, 0.0);
+        for _ in 0..cfg.iterations {
+            // Red-black ordering, so the result does not depend on sweep direction.
+            for color in [&s.red, &s.black] {
+                for &i in color {
+                    let i = i as usize;
+                    let sum: f32 = s.links[i].iter().map(|&j| p[j as usize]).sum();
+                    p[i] += SOR_OMEGA * ((sum - s.div[i]) * s.inv_count[i] - p[i]);
+                }
+            }
+        }
+        for &i in &s.cells {
+            let i = i as usize;
+            let [_, _, east, south] = s.links[i];
+            if (east as usize != n) {
+                s.u[i] = (s.u[i] - (p[east as usize] - p[i])).clamp(-MAX_SPEED, MAX_SPEED);
+            }
+            if (south as usize != n) {
+                s.v[i] = (s.v[i] - (p[south as usize] - p[i])).clamp(-MAX_SPEED, MAX_SPEED);
+            }
+        }
+
+        // Output the change from last tick.
+        let delta_vx = s.u.iter().zip(curr.cell_vel_x).map(|(u, old)| u - old).collect();
+        let delta_vy = s.v.iter().zip(curr.cell_vel_y).map(|(v, old)| v - old).collect();
+        ModuleOutput::DeltaVelocity { delta_vx, delta_vy }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&self.pressure)?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        self.pressure = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Boundary;
+    use crate::physics::command::{Region, WorldCommand};
+    use crate::physics::engine::Engine;
+    use crate::physics::util::{mat_name, world_from_rows};
+    use crate::world::World;
+
+    /// Build a world from rows of '.' (air), 'w' (water) and '#' (rock), with an engine
+    /// running only the fluid solver with the given config.
+    fn fluid_world(rows: &[&str], config: &str) -> (World, Engine) {
+        let (mut world, mat_db) = world_from_rows(rows, r#"
+            {
+                "base:air": ( phase: gas ),
+                "test:water": ( phase: liquid ),
+                "test:rock": (),
+            }
+        "#, |c| (mat_name(c), 20.0));
+
+        let config = ron::de::from_str(config).unwrap();
+        world.boundary = Boundary::from_config(&config, &mat_db).unwrap();
+        let mut engine = Engine::new(config, &world.mat_db, world.w, world.h).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleFluid::new(&curr));
+        (world, engine)
+    }
+
+    /// Sum of absolute net flow out of every cell of a world with no periodic edges and no
+    /// obstacles. Faces on the world edges count as closed.
+    fn total_divergence(world: &World) -> f32 {
+        let (w, h) = (world.w, world.h);
+        let u = |x: usize, y: usize| if (x + 1 < w) { world.cell_vel_x.cur[y * w + x] } else { 0.0 };
+        let v = |x: usize, y: usize| if (y + 1 < h) { world.cell_vel_y.cur[y * w + x] } else { 0.0 };
+        let mut total = 0.0;
+        for y in 0..h {
+            for x in 0..w {
+                let u_w = if (x > 0) { u(x - 1, y) } else { 0.0 };
+                let v_n = if (y > 0) { v(x, y - 1) } else { 0.0 };
+                total += (u(x, y) - u_w + v(x, y) - v_n).abs();
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn test_projection_removes_divergence() {
+        let (mut world, mut engine) = fluid_world(&[
+            "..........",
+            "..........",
+            "..........",
+            "..........",
+            "..........",
+            "..........",
+        ], "{}");
+
+        // A jet blowing right into the east wall.
+        for y in 2..4 {
+            for x in 2..5 {
+                world.cell_vel_x.cur[y * 10 + x] = 0.8;
+            }
+        }
+        world.sync_all();
+        let before = total_divergence(&world);
+
+        engine.step(&mut world);
+        let after = total_divergence(&world);
+        assert!(after < before * 0.05, "before: {}, after: {}", before, after);
+
+        // The jet turns back along the top and bottom, circulating rather than piling up.
+        let (curr, _) = world.ctx_pair();
+        assert!(curr.get_vel(4, 0).0 < 0.0 || curr.get_vel(4, 5).0 < 0.0);
+    }
+
+    #[test]
+    fn test_wind_drags_gas_but_not_obstacles() {
+        let (mut world, mut engine) = fluid_world(&[
+            "........",
+            "...#....",
+            "wwwwwwww",
+        ], r#"{ "fluid": { "wind_x": 0.5, "drag": 0.2 }, "boundary": { "west": "periodic", "east": "periodic" } }"#);
+
+        for _ in 0..50 {
+            engine.step(&mut world);
+        }
+
+        let (curr, _) = world.ctx_pair();
+        assert!(curr.get_vel(0, 0).0 > 0.1);
+        assert_eq!(curr.get_vel(3, 1), (0.0, 0.0));
+        for x in 0..8 {
+            let (vx, vy) = curr.get_vel(x, 0);
+            assert!(vx.abs() <= MAX_SPEED && vy.abs() <= MAX_SPEED);
+        }
+    }
+
+    #[test]
+    fn test_convection_lifts_hot_fluid() {
+        let rows = [
+            "#######",
+            "#.....#",
+            "#.....#",
+            "#.....#",
+            "#######",
+        ];
+        for convection in [0.0, 2.0] {
+            let (mut world, mut engine) = fluid_world(&rows, &format!(r#"{{ "fluid": {{ "convection": {} }} }}"#, convection));
+            world.cell_temps.cur[2 * 7 + 3] = 600.0;
+            world.sync_all();
+            for _ in 0..3 {
+                engine.step(&mut world);
+            }
+
+            // Hot air rises up the middle, and cooler air comes down the sides to replace it.
+            let (curr, _) = world.ctx_pair();
+            if (convection == 0.0) {
+                assert_eq!(curr.get_vel(3, 2), (0.0, 0.0));
+            } else {
+                assert!(curr.get_vel(3, 2).1 < -0.01, "vel: {:?}", curr.get_vel(3, 2));
+                assert!(curr.get_vel(1, 2).1 > 0.0, "vel: {:?}", curr.get_vel(1, 2));
+            }
+        }
+    }
+
+    #[test]
+    fn test_closed_pipe_cannot_flow() {
+        let (mut world, mut engine) = fluid_world(&[
+            "#######",
+            "#wwwww#",
+            "#######",
+        ], "{}");
+
+        // Liquid in a sealed pipe has nowhere to go, so a push is cancelled out.
+        engine.queue_command(WorldCommand::AddVelocity {
+            region: Region::Rect { x: 1, y: 1, w: 5, h: 1 },
+            vx: 0.5,
+            vy: 0.0,
+        });
+        engine.step(&mut world);
+        engine.step(&mut world);
+        let (curr, _) = world.ctx_pair();
+        for x in 1..6 {
+            assert!(curr.get_vel(x, 1).0.abs() < 0.05, "x: {}, vel: {:?}", x, curr.get_vel(x, 1));
+        }
+    }
+
+    #[test]
+    fn test_ambient_wraps_around_periodic_edges() {
+        let rows = vec![".............."; 12];
+        let periodic = r#"{ "boundary": { "north": "periodic", "south": "periodic", "west": "periodic", "east": "periodic" } }"#;
+
+        // Buoyancy of every cell of an uneven temperature field, shifted by `(sx, sy)`.
+        let lift = |config: &str, sx: usize, sy: usize| {
+            let (mut world, _) = fluid_world(&rows, config);
+            let (w, h) = (world.w, world.h);
+            for y in 0..h {
+                for x in 0..w {
+                    world.cell_temps.cur[((y + sy) % h) * w + (x + sx) % w] = ((x * 7 + y * 13) % 11) as f32 * 40.0;
+                }
+            }
+            world.sync_all();
+            let (curr, _) = world.ctx_pair();
+            let mut s = Scratch::default();
+            s.prepare(&curr);
+            s.add_buoyancy(&curr, 1.0);
+            (s.vc, w, h)
+        };
+
+        // With every edge periodic there is no edge, so shifting the field shifts the lift.
+        let (still, w, h) = lift(periodic, 0, 0);
+        let (shifted, _, _) = lift(periodic, 5, 3);
+        for y in 0..h {
+            for x in 0..w {
+                let (a, b) = (still[y * w + x], shifted[((y + 3) % h) * w + (x + 5) % w]);
+                assert!((a - b).abs() < 1e-6, "cell {:?}: {} vs {}", (x, y), a, b);
+            }
+        }
+
+        // Closed edges clip the window instead, so cells near them see different surroundings.
+        let (clipped, _, _) = lift("{}", 0, 0);
+        let diff = clipped.iter().zip(&still).fold(0.0f32, |a, (b, c)| a.max((b - c).abs()));
+        assert!(diff > 1e-2, "diff: {}", diff);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::material::Phase;
+use crate::physics::intent::CellIntent;
+use crate::physics::module::{Module, ModuleOutput};
+use crate::physics::module_fluid::{buoyancy, FluidConfig};
+use crate::physics::module_gas::effective_density;
+use crate::physics::util::rand_iter_dir;
+use crate::world::{CurrCtx, PostRunCtx};
+use rand::{Rng, SeedableRng};
+use anyhow::Result;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Moves liquid and gas cells along the fluid velocity field solved by `ModuleFluid`. Each
+/// tick, a cell steps one cell along each axis with a chance equal to its speed along that
+/// axis, swapping with the liquid or gas it moves into. As with `ModuleGravity` and
+/// `ModuleGas`, a cell only moves down into lighter fluid and up into denser fluid, so flow
+/// never lifts a liquid into the gas above it. Added before `ModuleGas`, so flow takes
+/// priority over random walks.
+///
+/// Cells that do not flow convect instead: a cell swaps with a colder cell of the same
+/// material above it, with a chance scaled by the config `fluid.convection` strength and the
+/// difference in buoyancy. This carries heat upward even where the velocity field is still,
+/// and seeds the plumes the solver then organizes. Different materials are left to gravity
+/// and `ModuleGas`.
+pub struct ModuleFluidFlow {
+    rng_a: Xoshiro256PlusPlus,
+    rng_b: Xoshiro256PlusPlus,
+    convection: f32,
+}
+
+impl ModuleFluidFlow {
+    pub fn new(_curr: &CurrCtx<'_>, rng_seed: u64) -> Self {
+        Self {
+            rng_a: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
+            rng_b: Xoshiro256PlusPlus::seed_from_u64(rng_seed ^ 0xBBBBBBBBBBBBBBBB),
+            convection: FluidConfig::default().convection,
+        }
+    }
+}
+
+impl Module for ModuleFluidFlow {
+
+    fn apply_config(&mut self, config: &HashMap<String, Value>) {
+        self.convection = FluidConfig::from_config(config).convection;
+    }
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+
+        let mut intents = vec![];
+
+        rand_iter_dir(&mut self.rng_a, curr.w, curr.h, |x, y| {
+
+            let a = curr.get_mat_id(x, y);
+            if (!matches!(curr.mat_db.phase_of(a), Phase::Liquid | Phase::Gas)) {
+                return;
+            }
+            let temp = curr.get_temp(x, y);
+
+            // Flow along the velocity field.
+            let (vx, vy) = curr.get_vel(x, y);
+            if (vx != 0.0 || vy != 0.0) {
+                let dx = if self.rng_b.random_range(0.0..1.0) < vx.abs() { vx.signum() as isize } else { 0 };
+                let dy = if self.rng_b.random_range(0.0..1.0) < vy.abs() { vy.signum() as isize } else { 0 };
+                if (dx != 0 || dy != 0) {
+                    if let Some((nx, ny)) = curr.neighbor_cell(x, y, dx, dy) {
+                        let b = curr.get_mat_id(nx, ny);
+                        let temp_b = curr.get_temp(nx, ny);
+                        let density = effective_density(curr, a, temp);
+                        let density_b = effective_density(curr, b, temp_b);
+                        let buoyant = match dy {
+                            1 => density_b <= density,
+                            -1 => density_b >= density,
+                            _ => true,
+                        };
+                        // Swapping identical cells changes nothing.
+                        if (matches!(curr.mat_db.phase_of(b), Phase::Liquid | Phase::Gas) && buoyant && (b != a || temp_b != temp)) {
+                            intents.push(CellIntent::MoveSwap { from: (x, y), to: (nx, ny) });
+                            return;
+                        }
+                    }
+                }
+            }
+
+            // Convection: rise through colder fluid of the same material. Only the lower cell
+            // of a pair acts.
+            if (self.convection > 0.0) {
+                if let Some((nx, ny)) = curr.neighbor_cell(x, y, 0, -1) {
+                    if (curr.get_mat_id(nx, ny) == a) {
+                        let lift = buoyancy(temp, curr.get_temp(nx, ny));
+                        if (lift > 0.0 && self.rng_b.random_range(0.0..1.0) < self.convection * lift) {
+                            intents.push(CellIntent::MoveSwap { from: (x, y), to: (nx, ny) });
+                        }
+                    }
+                }
+            }
+        });
+
+        ModuleOutput::CellIntents { intents }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(&self.rng_a, &self.rng_b))?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        (self.rng_a, self.rng_b) = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Boundary;
+    use crate::physics::engine::Engine;
+    use crate::physics::module_diffusion_thermal::ModuleDiffusionThermal;
+    use crate::physics::module_fluid::ModuleFluid;
+    use crate::physics::util::{mat_name, world_from_rows};
+
+    #[test]
+    fn test_wind_carries_smoke_around_obstacle() {
+        let (mut world, mat_db) = world_from_rows(&[
+            "........................",
+            "........................",
+            "......#.................",
+            ".m....#.................",
+            "......#.................",
+            "........................",
+            "........................",
+        ], r#"
+            {
+                "base:air": ( phase: gas ),
+                "test:smoke": ( phase: gas ),
+                "test:rock": (),
+            }
+        "#, |c| (if (c == 'm') { "test:smoke" } else { mat_name(c) }, 0.0));
+        let smoke = mat_db.get_id("test:smoke").unwrap();
+        let rock = mat_db.get_id("test:rock").unwrap();
+        let (w, h) = (world.w, world.h);
+
+        let config = ron::de::from_str(r#"{ "fluid": { "wind_x": 0.6, "drag": 0.3 }, "boundary": { "west": "periodic", "east": "periodic" } }"#).unwrap();
+        world.boundary = Boundary::from_config(&config, &mat_db).unwrap();
+        let mut engine = Engine::new(config, &world.mat_db, w, h).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleFluid::new(&curr));
+        engine.add(ModuleFluidFlow::new(&curr, 42));
+
+        for _ in 0..20 {
+            engine.step(&mut world);
+        }
+
+        // The smoke blew past the rock, which stayed put.
+        let i = world.cell_mat_ids.cur.iter().position(|&m| m == smoke).unwrap();
+        assert!(i % w > 6, "smoke at {:?}", (i % w, i / w));
+        assert_eq!(world.cell_mat_ids.cur.iter().filter(|&&m| m == rock).count(), 3);
+    }
+
+    #[test]
+    fn test_flow_cannot_lift_liquid_into_gas() {
+        let rows = [
+            "........",
+            "........",
+            "........",
+            "........",
+            "wwwwwwww",
+            "wwwwwwww",
+        ];
+        let (mut world, mat_db) = world_from_rows(&rows, r#"
+            {
+                "base:air": ( phase: gas, density: 1.2 ),
+                "test:water": ( phase: liquid, density: 1000.0 ),
+            }
+        "#, |c| (mat_name(c), 0.0));
+        let (w, h) = (world.w, world.h);
+
+        // A strong updraft everywhere, held fixed since there is no solver.
+        world.cell_vel_y.cur.fill(-0.9);
+        world.sync_all();
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, w, h).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleFluidFlow::new(&curr, 42));
+        for _ in 0..50 {
+            engine.step(&mut world);
+        }
+
+        // The water stays under the air.
+        for (i, &m) in world.cell_mat_ids.cur.iter().enumerate() {
+            let c = rows[i / w].as_bytes()[i % w] as char;
+            assert_eq!(m, mat_db.get_id(mat_name(c)).unwrap(), "cell {:?}", (i % w, i / w));
+        }
+    }
+
+    #[test]
+    fn test_convection_carries_heat_up() {
+        // Mean temperature of the top rows of a box of air over a hot floor.
+        let top_temp = |convection: f32| {
+            let mut rows = vec!["................"; 15];
+            rows.push("################");
+            let (mut world, _) = world_from_rows(&rows, r#"
+                {
+                    "base:air": ( phase: gas, diffusivity: 0.05 ),
+                    "test:rock": ( diffusivity: 0.25 ),
+                }
+            "#, |c| (mat_name(c), 20.0));
+            let (w, h) = (world.w, world.h);
+
+            let config = ron::de::from_str(&format!(r#"{{ "fluid": {{ "convection": {} }} }}"#, convection)).unwrap();
+            let mut engine = Engine::new(config, &world.mat_db, w, h).unwrap();
+            let (curr, _) = world.ctx_pair();
+            engine.add(ModuleDiffusionThermal::new(&curr, 7));
+            engine.add(ModuleFluid::new(&curr));
+            engine.add(ModuleFluidFlow::new(&curr, 42));
+
+            for _ in 0..300 {
+                for x in 0..w {
+                    world.cell_temps.cur[(h - 1) * w + x] = 800.0;
+                }
+                world.sync_all();
+                engine.step(&mut world);
+            }
+            world.cell_temps.cur[..4 * w].iter().sum::<f32>() / (4 * w) as f32
+        };
+
+        let still = top_temp(0.0);
+        let convecting = top_temp(1.0);
+        assert!(convecting > still + 20.0, "still: {}, convecting: {}", still, convecting);
+    }
+
+    #[test]
+    fn test_hot_liquid_stays_under_cold_gas() {
+        // A pool of lava ('l') with a step in its surface, which lifting the lava as a whole
+        // would stir.
+        let mut rows = vec!["................"; 11];
+        rows.push("llllllll........");
+        rows.extend(["llllllllllllllll"; 4]);
+
+        // Mean temperature of the air over the pool, and the fastest anything moved on the
+        // first tick.
+        let run = |convection: f32| {
+            let (mut world, mat_db) = world_from_rows(&rows, r#"
+                {
+                    "base:air": ( phase: gas, density: 1.2, diffusivity: 0.05 ),
+                    "test:lava": ( phase: liquid, density: 3000.0, diffusivity: 0.05 ),
+                }
+            "#, |c| if (c == 'l') { ("test:lava", 1200.0) } else { (mat_name(c), 20.0) });
+            let lava = mat_db.get_id("test:lava").unwrap();
+            let (w, h) = (world.w, world.h);
+
+            let config = ron::de::from_str(&format!(r#"{{ "fluid": {{ "convection": {} }} }}"#, convection)).unwrap();
+            let mut engine = Engine::new(config, &world.mat_db, w, h).unwrap();
+            let (curr, _) = world.ctx_pair();
+            engine.add(ModuleDiffusionThermal::new(&curr, 7));
+            engine.add(ModuleFluid::new(&curr));
+            engine.add(ModuleFluidFlow::new(&curr, 42));
+
+            // Each phase starts at one temperature, so nothing is buoyant yet.
+            engine.step(&mut world);
+            let speed = world.cell_vel_y.cur.iter().fold(0.0f32, |a, v| a.max(v.abs()));
+            for _ in 0..200 {
+                engine.step(&mut world);
+            }
+
+            // The lava stays in its pool.
+            for (i, &m) in world.cell_mat_ids.cur.iter().enumerate() {
+                let in_pool = rows[i / w].as_bytes()[i % w] == b'l';
+                assert_eq!(m == lava, in_pool, "cell {:?}", (i % w, i / w));
+            }
+            (world.cell_temps.cur[..8 * w].iter().sum::<f32>() / (8 * w) as f32, speed)
+        };
+
+        // The lava is not lifted by the colder air above it, but the air it heats rises in
+        // plumes.
+        let (still, _) = run(0.0);
+        let (convecting, speed) = run(1.0);
+        assert!(speed < 1e-4, "speed: {}", speed);
+        assert!(convecting > still + 20.0, "still: {}, convecting: {}", still, convecting);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::boundary::Neighbor;
+use crate::material::{MaterialId, Phase};
+use crate::physics::intent::CellIntent;
+use crate::physics::module::{Module, ModuleOutput};
+use crate::physics::util::{rand_iter_dir, try_random_dirs, NEIGHBORS_8};
+use crate::world::{CurrCtx, PostRunCtx, GAS_REFERENCE_TEMP, KELVIN};
+use rand::{Rng, SeedableRng};
+use anyhow::Result;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Pressure drop to a neighboring gas needed to push a gas into it, rather than random walk.
+const PUSH_PRESSURE_DROP: f32 = 0.05;
+
+/// Moves every material with the gas phase. Gases rise through denser gases and liquids above
+/// them, with a chance proportional to the density difference. Gas density falls with
+/// temperature, so hot gases rise faster. Gases are pushed into neighboring gases at lower
+/// pressure, and otherwise random-walk into neighboring gases, and
+/// materials with a `fade_chance` turn into their `fade_product` over time. Gas that rises or
+/// walks across a void edge is deleted.
+pub struct ModuleGas {
+    rng_a: Xoshiro256PlusPlus,
+    rng_b: Xoshiro256PlusPlus,
+    checkerboard_toggle: bool,
+}
+
+impl ModuleGas {
+    pub fn new(_curr: &CurrCtx<'_>, rng_seed: u64) -> Self {
+        Self {
+            rng_a: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
+            rng_b: Xoshiro256PlusPlus::seed_from_u64(rng_seed ^ 0xBBBBBBBBBBBBBBBB),
+            checkerboard_toggle: false,
+        }
+    }
+}
+
+/// True if any neighbor of (x, y) at the given offsets differs from `id`. A void edge differs
+/// unless `id` is the material it leaves behind. Most gas cells are surrounded by the same
+/// gas, and this lets them skip all RNG calls.
+fn any_neighbor_differs(curr: &CurrCtx<'_>, x: usize, y: usize, id: MaterialId, offsets: &[(isize, isize)]) -> bool {
+    offsets.iter().any(|&(dx, dy)| {
+        match curr.neighbor(x, y, dx, dy) {
+            Neighbor::Cell(nx, ny) => curr.get_mat_id(nx, ny) != id,
+            Neighbor::Void => id != curr.boundary.void_mat,
+            Neighbor::Wall => false,
+        }
+    })
+}
+
+/// Density of a material at the given temperature. Gases follow the ideal gas law; other
+/// phases are treated as incompressible.
+pub(crate) fn effective_density(curr: &CurrCtx<'_>, id: MaterialId, temp: f32) -> f32 {
+    let density = curr.mat_db.density_of(id);
+    if (curr.mat_db.phase_of(id) == Phase::Gas) {
+        density * (GAS_REFERENCE_TEMP + KELVIN) / (temp + KELVIN).max(1.0)
+    } else {
+        density
+    }
+}
+
+impl Module for ModuleGas {
+
+    fn apply_config(&mut self, _config: &HashMap<String, Value>) {}
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+
+        let mut intents = vec![];
+
+        // Random walk intents are applied after fade and buoyancy intents. Otherwise a gas
+        // surrounded by a different gas would be swapped around by its neighbors every tick,
+        // and never get to fade or rise itself.
+        let mut walks = vec![];
+
+        self.checkerboard_toggle = !self.checkerboard_toggle;
+
+        rand_iter_dir(&mut self.rng_a, curr.w, curr.h, |x, y| {
+
+            let a = curr.get_mat_id(x, y);
+            if (curr.mat_db.phase_of(a) != Phase::Gas) {
+                return;
+            }
+
+            // Chance to fade.
+            if let Some(mat) = curr.mat_db.get(a) {
+                if let Some(product) = mat.fade_product_id {
+                    if self.rng_b.random_range(0.0..1.0) < mat.fade_chance {
+                        intents.push(CellIntent::Transform { cell: (x, y), out: product });
+                        return;
+                    }
+                }
+            }
+
+            // Buoyancy: rise through denser fluids above. Only the lower cell of a pair acts, so
+            // a heavy gas sinks by the lighter gas beneath it rising. Check straight up before
+            // diagonals, trying a random side first.
+            if any_neighbor_differs(curr, x, y, a, &NEIGHBORS_8[0..3]) {
+                let density = effective_density(curr, a, curr.get_temp(x, y));
+                let side = if self.rng_b.random_bool(0.5) { 1 } else { -1 };
+                for dx in [0, side, -side] {
+                    let (nx, ny) = match curr.neighbor(x, y, dx, -1) {
+                        Neighbor::Cell(nx, ny) => (nx, ny),
+                        Neighbor::Void if a != curr.boundary.void_mat => {
+                            intents.push(CellIntent::Transform { cell: (x, y), out: curr.boundary.void_mat });
+                            return;
+                        }
+                        Neighbor::Void | Neighbor::Wall => continue,
+                    };
+                    let b = curr.get_mat_id(nx, ny);
+                    if (b == a || !matches!(curr.mat_db.phase_of(b), Phase::Gas | Phase::Liquid)) { continue; }
+
+                    let density_b = effective_density(curr, b, curr.get_temp(nx, ny));
+                    if (density_b > density) {
+                        if self.rng_b.random_range(0.0..1.0) < 1.0 - density / density_b {
+                            intents.push(CellIntent::MoveSwap { from: (x, y), to: (nx, ny) });
+                            return;
+                        }
+                        break;
+                    }
+                }
+            }
+
+            // Checkerboard: False, skip evens. True, skip odds.
+            // Only random walk is gated, since a gas that keeps being swapped around changes
+            // parity every tick.
+            if ((x + y) & 1) == self.checkerboard_toggle as usize {
+                return;
+            }
+            if (!any_neighbor_differs(curr, x, y, a, &NEIGHBORS_8)) {
+                return;
+            }
+
+            // Pressure: pushed into the neighboring gas with the lowest pressure, if it is low
+            // enough. Ties are broken randomly.
+            let mut push = None;
+            let mut lowest = curr.get_pressure(x, y) - PUSH_PRESSURE_DROP;
+            try_random_dirs(&mut self.rng_b, true, |(dx, dy)| {
+                if let Some((nx, ny)) = curr.neighbor_cell(x, y, dx, dy) {
+                    let b = curr.get_mat_id(nx, ny);
+                    if (b != a && curr.mat_db.phase_of(b) == Phase::Gas && curr.get_pressure(nx, ny) < lowest) {
+                        lowest = curr.get_pressure(nx, ny);
+                        push = Some((nx, ny));
+                    }
+                }
+                false
+            });
+            if let Some(to) = push {
+                walks.push(CellIntent::MoveSwap { from: (x, y), to });
+                return;
+            }
+
+            // Random walk into neighboring gases.
+            try_random_dirs(&mut self.rng_b, false, |(dx, dy)| {
+                match curr.neighbor(x, y, dx, dy) {
+                    Neighbor::Cell(nx, ny) => {
+                        let b = curr.get_mat_id(nx, ny);
+                        if (b != a && curr.mat_db.phase_of(b) == Phase::Gas) {
+                            walks.push(CellIntent::MoveSwap { from: (x, y), to: (nx, ny) });
+                            return true;
+                        }
+                        false
+                    }
+                    Neighbor::Void if a != curr.boundary.void_mat => {
+                        walks.push(CellIntent::Transform { cell: (x, y), out: curr.boundary.void_mat });
+                        true
+                    }
+                    Neighbor::Void | Neighbor::Wall => false,
+                }
+            });
+        });
+
+        intents.append(&mut walks);
+        ModuleOutput::CellIntents { intents }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(&self.rng_a, &self.rng_b, self.checkerboard_toggle))?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        (self.rng_a, self.rng_b, self.checkerboard_toggle) = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialDb;
+    use crate::physics::engine::Engine;
+    use crate::physics::util::world_from_rows;
+    use crate::world::World;
+    use std::sync::Arc;
+
+    /// A tall box of air with one cell of `gas` at the bottom, at the given temperatures,
+    /// and an engine running only the gas module.
+    fn gas_column(gas: &str, gas_temp: f32, air_temp: f32) -> (World, Engine, Arc<MaterialDb>) {
+        let mut rows = vec!["....."; 39];
+        rows.push("..g..");
+        let (mut world, mat_db) = world_from_rows(&rows, r#"
+            {
+                "base:air": ( phase: gas, density: 1.2 ),
+                "test:heavy": ( phase: gas, density: 2.0 ),
+                "test:smoke": ( phase: gas, density: 1.2, fade_chance: 1.0, fade_product_name: "base:air" ),
+            }
+        "#, |c| if (c == 'g') { (gas, gas_temp) } else { ("base:air", air_temp) });
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, world.w, world.h).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleGas::new(&curr, 42));
+        (world, engine, mat_db)
+    }
+
+    fn height_of(world: &World, id: MaterialId) -> usize {
+        let i = world.cell_mat_ids.cur.iter().position(|&m| m == id).unwrap();
+        world.h - 1 - i / world.w
+    }
+
+    #[test]
+    fn test_gas_hot_rises_faster() {
+        let mut heights = vec![];
+        for gas_temp in [100.0, 1000.0] {
+            let (mut world, mut engine, mat_db) = gas_column("test:heavy", gas_temp, 20.0);
+            for _ in 0..30 {
+                engine.step(&mut world);
+            }
+            heights.push(height_of(&world, mat_db.get_id("test:heavy").unwrap()));
+        }
+
+        // Heavy gas is lighter than air when hot enough, and rises faster the hotter it is.
+        assert!(heights[0] < heights[1], "heights: {:?}", heights);
+        assert!(heights[1] > 10, "heights: {:?}", heights);
+    }
+
+    #[test]
+    fn test_gas_pushed_toward_lower_pressure() {
+        let (mut world, mut engine, mat_db) = gas_column("test:heavy", 20.0, 20.0);
+        let heavy = mat_db.get_id("test:heavy").unwrap();
+
+        // Thin out the air left of the heavy gas. Nothing equalizes it in this test.
+        for y in 0..world.h {
+            for x in 0..2 {
+                world.cell_gas_mass.cur[y * world.w + x] = 0.2;
+            }
+        }
+        world.sync_all();
+
+        for _ in 0..20 {
+            engine.step(&mut world);
+        }
+        let i = world.cell_mat_ids.cur.iter().position(|&m| m == heavy).unwrap();
+        assert!(i % world.w < 2, "x: {}", i % world.w);
+    }
+
+    #[test]
+    fn test_gas_fades_into_product() {
+        let (mut world, mut engine, mat_db) = gas_column("test:smoke", 20.0, 20.0);
+        let smoke = mat_db.get_id("test:smoke").unwrap();
+        for _ in 0..10 {
+            engine.step(&mut world);
+        }
+        assert!(!world.cell_mat_ids.cur.contains(&smoke));
+    }
+}
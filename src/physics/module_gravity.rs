@@ -0,0 +1,258 @@
+use crate::boundary::Neighbor;
+use crate::material::Phase;
+use crate::physics::intent::CellIntent;
+use crate::physics::module::{Module, ModuleOutput};
+use crate::physics::util::rand_iter_dir;
+use crate::world::{CurrCtx, PostRunCtx};
+use rand::{Rng, SeedableRng};
+use anyhow::Result;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Moves powders and liquids down through lighter non-solid materials, using each
+/// material's `phase` and `density`. Powders fall, or slide diagonally off piles. Liquids
+/// do the same, then spread horizontally when they can't fall. Solids never move, and
+/// gases are left to the gas modules. Cells that move across a void edge are deleted.
+pub struct ModuleGravity {
+    rng_a: Xoshiro256PlusPlus,
+    rng_b: Xoshiro256PlusPlus,
+    checkerboard_toggle: bool,
+}
+
+impl ModuleGravity {
+    pub fn new(_curr: &CurrCtx<'_>, rng_seed: u64) -> Self {
+        Self {
+            rng_a: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
+            rng_b: Xoshiro256PlusPlus::seed_from_u64(rng_seed ^ 0xBBBBBBBBBBBBBBBB),
+            checkerboard_toggle: false,
+        }
+    }
+}
+
+/// True if a cell of the given density can swap into (x, y).
+fn can_displace(curr: &CurrCtx<'_>, density: f32, x: usize, y: usize) -> bool {
+    let b = curr.get_mat_id(x, y);
+    curr.mat_db.phase_of(b) != Phase::Solid && curr.mat_db.density_of(b) < density
+}
+
+impl Module for ModuleGravity {
+
+    fn apply_config(&mut self, _config: &HashMap<String, Value>) {}
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+
+        let mut intents = vec![];
+
+        self.checkerboard_toggle = !self.checkerboard_toggle;
+
+        rand_iter_dir(&mut self.rng_a, curr.w, curr.h, |x, y| {
+
+            // Checkerboard: False, skip evens. True, skip odds.
+            // A cell that falls or spreads sideways changes parity, so it moves again next
+            // tick. A diagonal slide keeps its parity, so a cell sliding down a slope only
+            // moves every other tick, half as fast as a fall.
+            if ((x + y) & 1) == self.checkerboard_toggle as usize {
+                return;
+            }
+
+            let a = curr.get_mat_id(x, y);
+            let phase = curr.mat_db.phase_of(a);
+            if (phase != Phase::Powder && phase != Phase::Liquid) {
+                return;
+            }
+            let density = curr.mat_db.density_of(a);
+
+            let mut try_move = |dx: isize, dy: isize| {
+                match curr.neighbor(x, y, dx, dy) {
+                    Neighbor::Cell(nx, ny) if can_displace(curr, density, nx, ny) => {
+                        intents.push(CellIntent::MoveSwap { from: (x, y), to: (nx, ny) });
+                        true
+                    }
+                    Neighbor::Void => {
+                        intents.push(CellIntent::Transform { cell: (x, y), out: curr.boundary.void_mat });
+                        true
+                    }
+                    _ => false,
+                }
+            };
+
+            // Fall straight down.
+            if try_move(0, 1) { return; }
+
+            // Slide diagonally down, trying a random side first.
+            let side = if self.rng_b.random_bool(0.5) { 1 } else { -1 };
+            if try_move(side, 1) || try_move(-side, 1) { return; }
+
+            // Liquids spread sideways.
+            if (phase == Phase::Liquid) {
+                let _ = try_move(side, 0) || try_move(-side, 0);
+            }
+        });
+
+        ModuleOutput::CellIntents { intents }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(&self.rng_a, &self.rng_b, self.checkerboard_toggle))?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        (self.rng_a, self.rng_b, self.checkerboard_toggle) = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::{Boundary, EdgeMode};
+    use crate::material::MaterialDb;
+    use crate::physics::engine::Engine;
+    use crate::physics::util::{mat_name, row_string, world_from_rows};
+    use crate::world::World;
+    use std::sync::Arc;
+
+    /// Build a world from rows of '.' (air), 's' (sand), 'w' (water) and '#' (rock), with
+    /// an engine running only gravity.
+    fn gravity_world(rows: &[&str]) -> (World, Engine, Arc<MaterialDb>) {
+        let (mut world, mat_db) = world_from_rows(rows, r#"
+            {
+                "base:air": ( phase: gas, density: 1.2 ),
+                "test:rock": ( density: 2600.0 ),
+                "test:sand": ( phase: powder, density: 1600.0 ),
+                "test:water": ( phase: liquid, density: 1000.0 ),
+            }
+        "#, |c| (mat_name(c), 0.0));
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, world.w, world.h).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleGravity::new(&curr, 42));
+        (world, engine, mat_db)
+    }
+
+    #[test]
+    fn test_gravity_powder_falls_and_piles() {
+        let (mut world, mut engine, mat_db) = gravity_world(&[
+            "..s..",
+            "..s..",
+            "..s..",
+            ".....",
+            ".....",
+        ]);
+        for _ in 0..50 {
+            engine.step(&mut world);
+        }
+
+        // A column of three grains ends as a pile: two on the floor on either side of one
+        // that landed first, and none stacked on top of an empty side.
+        assert_eq!(row_string(&world, &mat_db, 4), ".sss.");
+        assert_eq!(row_string(&world, &mat_db, 3), ".....");
+    }
+
+    #[test]
+    fn test_gravity_liquid_spreads() {
+        let (mut world, mut engine, mat_db) = gravity_world(&[
+            "..w..",
+            "..w..",
+            "..w..",
+            "#####",
+        ]);
+        for _ in 0..200 {
+            engine.step(&mut world);
+        }
+
+        // Liquid levels out into a single layer on the floor.
+        assert_eq!(row_string(&world, &mat_db, 3), "#####");
+        assert_eq!(row_string(&world, &mat_db, 2).matches('w').count(), 3);
+        assert_eq!(row_string(&world, &mat_db, 1), ".....");
+    }
+
+    #[test]
+    fn test_gravity_denser_sinks_and_solids_stay() {
+        let (mut world, mut engine, mat_db) = gravity_world(&[
+            "sss",
+            "www",
+            "www",
+        ]);
+        for _ in 0..50 {
+            engine.step(&mut world);
+        }
+
+        // Sand sinks through water.
+        assert_eq!(row_string(&world, &mat_db, 0), "www");
+        assert_eq!(row_string(&world, &mat_db, 2), "sss");
+
+        // Rock never moves, even with nothing under it.
+        let (mut world, mut engine, mat_db) = gravity_world(&[
+            ".#.",
+            "...",
+        ]);
+        for _ in 0..10 {
+            engine.step(&mut world);
+        }
+        assert_eq!(row_string(&world, &mat_db, 0), ".#.");
+    }
+
+    #[test]
+    fn test_gravity_void_and_periodic_edges() {
+        // Sand falling through a void floor is deleted.
+        let (mut world, mut engine, mat_db) = gravity_world(&[
+            ".s.",
+            "...",
+        ]);
+        world.boundary = Boundary { south: EdgeMode::Void, void_mat: mat_db.get_id("base:air").unwrap(), ..Boundary::default() };
+        for _ in 0..10 {
+            engine.step(&mut world);
+        }
+        assert_eq!(row_string(&world, &mat_db, 0), "...");
+        assert_eq!(row_string(&world, &mat_db, 1), "...");
+
+        // Sand falling through a periodic floor comes back in at the top, and lands on rock.
+        let (mut world, mut engine, mat_db) = gravity_world(&[
+            "...",
+            "###",
+            ".s.",
+        ]);
+        world.boundary = Boundary { north: EdgeMode::Periodic, south: EdgeMode::Periodic, ..Boundary::default() };
+        for _ in 0..10 {
+            engine.step(&mut world);
+        }
+        assert_eq!(row_string(&world, &mat_db, 0), ".s.");
+        assert_eq!(row_string(&world, &mat_db, 2), "...");
+    }
+
+    #[test]
+    fn test_gravity_slide_speed() {
+        // A grain on a ramp slides diagonally one cell every other tick, three cells in five ticks.
+        let (mut world, mut engine, mat_db) = gravity_world(&[
+            "s......",
+            "#......",
+            "##.....",
+            "###....",
+            "####...",
+            "#####..",
+            "######.",
+        ]);
+        for _ in 0..5 {
+            engine.step(&mut world);
+        }
+        assert_eq!(row_string(&world, &mat_db, 3), "###s...");
+
+        // A falling grain moves every tick.
+        let (mut world, mut engine, mat_db) = gravity_world(&[
+            "s",
+            ".",
+            ".",
+            ".",
+            ".",
+            ".",
+        ]);
+        for _ in 0..5 {
+            engine.step(&mut world);
+        }
+        assert_eq!(row_string(&world, &mat_db, 5), "s");
+    }
+}
@@ -0,0 +1,175 @@
+use crate::boundary::Neighbor;
+use crate::material::Phase;
+use crate::physics::module::{Module, ModuleOutput};
+use crate::physics::util::NEIGHBORS_4;
+use crate::world::{gas_pressure, CurrCtx, PostRunCtx, GAS_REFERENCE_TEMP, KELVIN};
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Fraction of a pressure difference that flows across an edge each tick. Each cell has up to
+/// four edges, so this must stay at or below 1/8 to keep the explicit scheme stable and gas
+/// mass from going negative.
+const FLOW_RATE: f32 = 0.1;
+
+/// Equalizes gas pressure. Gas mass flows from each gas cell into neighboring gas cells at
+/// lower pressure, whatever the gases are. Pressure follows the ideal gas law, so a sealed
+/// chamber of gas that is heated rises in pressure and pushes on its walls; `ModuleGas` uses
+/// pressure differences to push gases toward lower pressure. Non-gas cells are walls, and void
+/// world edges vent to reference pressure. Mass is conserved everywhere else.
+pub struct ModulePressure {}
+
+impl ModulePressure {
+    pub fn new(_curr: &CurrCtx<'_>) -> Self {
+        Self {}
+    }
+}
+
+impl Module for ModulePressure {
+
+    fn apply_config(&mut self, _config: &HashMap<String, Value>) {}
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+        let w = curr.w;
+        let h = curr.h;
+        let temps = curr.get_temps();
+        let mat_ids = curr.get_mat_ids();
+
+        // Absolute temperature relative to reference, so pressure is mass * theta.
+        let theta = |i: usize| (temps[i] + KELVIN).max(1.0) / (GAS_REFERENCE_TEMP + KELVIN);
+
+        let mut delta_mass = vec![0.0; w * h];
+
+        for y in 0..h {
+            for x in 0..w {
+                let i = y * w + x;
+                if (curr.mat_db.phase_of(mat_ids[i]) != Phase::Gas) { continue; }
+
+                let theta_i = theta(i);
+                let p_i = gas_pressure(curr.cell_gas_mass[i], temps[i]);
+
+                // Flow is weighted by both cells' temperatures, so the same amount leaves one
+                // cell as enters the other.
+                for &(dx, dy) in &NEIGHBORS_4 {
+                    delta_mass[i] += match curr.neighbor(x, y, dx, dy) {
+                        Neighbor::Cell(nx, ny) => {
+                            let j = ny * w + nx;
+                            if (curr.mat_db.phase_of(mat_ids[j]) != Phase::Gas) { continue; }
+                            let p_j = gas_pressure(curr.cell_gas_mass[j], temps[j]);
+                            FLOW_RATE * 2.0 * (p_j - p_i) / (theta_i + theta(j))
+                        }
+                        Neighbor::Void => FLOW_RATE * (1.0 - p_i) / theta_i,
+                        Neighbor::Wall => 0.0,
+                    };
+                }
+            }
+        }
+
+        ModuleOutput::DeltaGasMass { delta_mass }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn load_state(&mut self, _bytes: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::{Boundary, EdgeMode};
+    use crate::physics::engine::Engine;
+    use crate::physics::util::world_from_rows;
+    use crate::world::World;
+
+    /// Build a world from rows of '.' (air) and '#' (rock), with an engine running only pressure.
+    fn pressure_world(rows: &[&str]) -> (World, Engine) {
+        let (mut world, _) = world_from_rows(rows, r#"{ "base:air": ( phase: gas ), "test:rock": () }"#, |c| {
+            (if (c == '#') { "test:rock" } else { "base:air" }, GAS_REFERENCE_TEMP)
+        });
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, world.w, world.h).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModulePressure::new(&curr));
+        (world, engine)
+    }
+
+    fn total_gas_mass(world: &mut World) -> f32 {
+        let (curr, _) = world.ctx_pair();
+        (0..curr.w * curr.h)
+            .filter(|&i| curr.mat_db.phase_of(curr.get_mat_id_i(i)) == Phase::Gas)
+            .map(|i| curr.cell_gas_mass[i])
+            .sum()
+    }
+
+    #[test]
+    fn test_pressure_equalizes_and_conserves_mass() {
+        let (mut world, mut engine) = pressure_world(&[
+            "....#..",
+            "....#..",
+            ".......",
+        ]);
+        world.cell_gas_mass.cur[0] = 4.0;
+        world.cell_temps.cur[6] = 300.0;
+        world.sync_all();
+        let mass = total_gas_mass(&mut world);
+
+        for _ in 0..2000 {
+            engine.step(&mut world);
+        }
+
+        // Mass is conserved, and pressure is the same everywhere, hot or cold.
+        assert!((total_gas_mass(&mut world) - mass).abs() < 1e-3);
+        let (curr, _) = world.ctx_pair();
+        let p = curr.get_pressure(0, 0);
+        for (x, y) in [(3, 0), (6, 0), (6, 2)] {
+            assert!((curr.get_pressure(x, y) - p).abs() < 1e-3);
+        }
+        // Rock holds no extra gas.
+        assert_eq!(curr.get_gas_mass(4, 0), 1.0);
+        // The hot cell holds less gas at the same pressure.
+        assert!(curr.get_gas_mass(6, 0) < curr.get_gas_mass(0, 0));
+    }
+
+    #[test]
+    fn test_heated_sealed_chamber_gains_pressure() {
+        let (mut world, mut engine) = pressure_world(&[
+            "#####",
+            "#...#",
+            "#...#",
+            "#####",
+        ]);
+        for _ in 0..20 {
+            engine.step(&mut world);
+        }
+        assert!((world.ctx_pair().0.get_pressure(2, 1) - 1.0).abs() < 1e-4);
+
+        // Gas cannot escape the chamber, so heating it raises its pressure.
+        for t in world.cell_temps.cur.iter_mut() {
+            *t = 313.15;
+        }
+        world.sync_all();
+        for _ in 0..20 {
+            engine.step(&mut world);
+        }
+        assert!((world.ctx_pair().0.get_pressure(2, 1) - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_void_edge_vents_pressure() {
+        let (mut world, mut engine) = pressure_world(&["...", "..."]);
+        world.boundary = Boundary { east: EdgeMode::Void, ..Boundary::default() };
+        world.cell_gas_mass.cur.fill(3.0);
+        world.sync_all();
+
+        for _ in 0..500 {
+            engine.step(&mut world);
+        }
+        assert!((world.ctx_pair().0.get_pressure(0, 0) - 1.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,240 @@
+use crate::physics::module::{Module, ModuleOutput};
+use crate::physics::util::NEIGHBORS_4;
+use crate::world::{CurrCtx, PostRunCtx, KELVIN};
+use rand::{Rng, SeedableRng};
+use anyhow::Result;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+/// Temperature, in Kelvin, at which a black body radiates `strength` heat per tick.
+const RADIATION_REFERENCE_TEMP: f32 = 1000.0;
+
+/// Rays are dropped once they carry less heat than this.
+const MIN_RAY_HEAT: f32 = 1e-4;
+
+/// Radiation settings, read from the `radiation` section of config.
+#[derive(Deserialize, Copy, Clone, Debug)]
+#[serde(default)]
+struct RadiationConfig {
+    /// Cells at or below this temperature do not radiate.
+    threshold: f32,
+    /// Heat radiated per tick by a black body at 1000 K. Scales with the fourth power of
+    /// absolute temperature. 0.0 turns radiation off.
+    strength: f32,
+    /// Rays cast per radiating cell per tick, spread evenly around the circle.
+    rays: u32,
+    /// Distance in cells after which a ray's remaining heat is lost.
+    range: f32,
+}
+
+impl Default for RadiationConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 300.0,
+            strength: 10.0,
+            rays: 16,
+            range: 64.0,
+        }
+    }
+}
+
+/// Radiates heat from hot cells along lines of sight. Each cell above the threshold
+/// temperature gives off heat proportional to its emissivity and the fourth power of its
+/// absolute temperature, split across rays cast in all directions. The ray set is rotated at
+/// random each tick, so over time every direction is covered. Each cell a ray crosses absorbs
+/// its `opacity` of the ray's heat, so rays pass through air and stop at the first opaque
+/// cell. Heat leaving the world or the range is lost. A cell never radiates itself below the
+/// threshold. Cells boxed in by opaque neighbors are skipped and left to conduction.
+pub struct ModuleRadiation {
+    rng: Xoshiro256PlusPlus,
+    config: RadiationConfig,
+}
+
+impl ModuleRadiation {
+    pub fn new(_curr: &CurrCtx<'_>, rng_seed: u64) -> Self {
+        Self {
+            rng: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
+            config: RadiationConfig::default(),
+        }
+    }
+}
+
+impl Module for ModuleRadiation {
+
+    fn apply_config(&mut self, config: &HashMap<String, Value>) {
+        if let Some(v) = config.get("radiation") {
+            self.config = RadiationConfig::deserialize(v).expect("Invalid config: radiation");
+        }
+    }
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+        let w = curr.w;
+        let h = curr.h;
+        let cfg = self.config;
+        let mut delta_temp = vec![0.0; w * h];
+        if (cfg.strength <= 0.0 || cfg.rays == 0) {
+            return ModuleOutput::DeltaTemp { delta_temp };
+        }
+
+        let temps = curr.get_temps();
+        let mat_ids = curr.get_mat_ids();
+        let emissivity_of = curr.mat_db.get_emissivity_lookup();
+        let opacity_of = curr.mat_db.get_opacity_lookup();
+        let heat_capacity_of = curr.mat_db.get_heat_capacity_lookup();
+        let opacity = |i: usize| opacity_of[mat_ids[i].0 as usize];
+
+        let rotation = self.rng.random_range(0.0..TAU);
+        let rays: Vec<(f32, f32)> = (0..cfg.rays)
+            .map(|r| {
+                let angle = rotation + TAU * r as f32 / cfg.rays as f32;
+                (angle.cos(), angle.sin())
+            })
+            .collect();
+
+        for y in 0..h {
+            for x in 0..w {
+                let i = y * w + x;
+                let t = temps[i];
+                if (t <= cfg.threshold) { continue; }
+                let emissivity = emissivity_of[mat_ids[i].0 as usize];
+                if (emissivity == 0.0) { continue; }
+
+                // Every ray's first step is into a 4-neighbor, so a boxed in cell only
+                // trades heat with its neighbors, which conduction already does.
+                let boxed_in = NEIGHBORS_4.iter().all(|&(dx, dy)| {
+                    curr.neighbor_cell(x, y, dx, dy).is_some_and(|(nx, ny)| opacity(ny * w + nx) >= 1.0)
+                });
+                if (boxed_in) { continue; }
+
+                let heat_capacity = heat_capacity_of[mat_ids[i].0 as usize];
+                let scaled = (t + KELVIN) / RADIATION_REFERENCE_TEMP;
+                let heat = (emissivity * cfg.strength * scaled.powi(4)).min((t - cfg.threshold) * heat_capacity);
+                delta_temp[i] -= heat / heat_capacity;
+
+                let ray_heat = heat / cfg.rays as f32;
+                for &(dir_x, dir_y) in &rays {
+                    // Walk the cells the ray crosses, one edge at a time, starting from the
+                    // center of the emitting cell.
+                    let step_x = if (dir_x < 0.0) { -1 } else { 1 };
+                    let step_y = if (dir_y < 0.0) { -1 } else { 1 };
+                    let delta_x = 1.0 / dir_x.abs();
+                    let delta_y = 1.0 / dir_y.abs();
+                    let mut next_x = 0.5 * delta_x;
+                    let mut next_y = 0.5 * delta_y;
+                    let (mut cx, mut cy) = (x, y);
+                    let mut left = ray_heat;
+
+                    while (left > MIN_RAY_HEAT) {
+                        let (dist, dx, dy) = if (next_x < next_y) {
+                            next_x += delta_x;
+                            (next_x - delta_x, step_x, 0)
+                        } else {
+                            next_y += delta_y;
+                            (next_y - delta_y, 0, step_y)
+                        };
+                        if (dist > cfg.range) { break; }
+                        let Some((nx, ny)) = curr.neighbor_cell(cx, cy, dx, dy) else { break };
+                        (cx, cy) = (nx, ny);
+
+                        let j = cy * w + cx;
+                        let absorbed = left * opacity(j);
+                        delta_temp[j] += absorbed / heat_capacity_of[mat_ids[j].0 as usize];
+                        left -= absorbed;
+                    }
+                }
+            }
+        }
+
+        ModuleOutput::DeltaTemp { delta_temp }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&self.rng)?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        self.rng = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::engine::Engine;
+    use crate::physics::util::world_from_rows;
+    use crate::world::World;
+
+    /// Build a world from rows of '.' (air), '#' (rock) and 'L' (lava at 1000°), with an engine
+    /// running only radiation. Everything else starts at 20°.
+    fn radiation_world(rows: &[&str]) -> (World, Engine) {
+        let (mut world, _) = world_from_rows(rows, r#"
+            {
+                "base:air": ( phase: gas, opacity: 0.0 ),
+                "test:rock": ( heat_capacity: 2.0 ),
+                "test:lava": ( phase: liquid, heat_capacity: 2.5, emissivity: 0.9 ),
+            }
+        "#, |c| match c {
+            '#' => ("test:rock", 20.0),
+            'L' => ("test:lava", 1000.0),
+            _ => ("base:air", 20.0),
+        });
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, world.w, world.h).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleRadiation::new(&curr, 42));
+        (world, engine)
+    }
+
+    #[test]
+    fn test_lava_warms_wall_across_air_gap() {
+        let (mut world, mut engine) = radiation_world(&[
+            "#.....##",
+            "#......#",
+            "#...LLL#",
+            "########",
+        ]);
+        for _ in 0..50 {
+            engine.step(&mut world);
+        }
+
+        let (curr, _) = world.ctx_pair();
+        // The far wall warmed across the gap, the air between did not, and the lava cooled.
+        assert!(curr.get_temp(0, 1) > 30.0, "wall: {}", curr.get_temp(0, 1));
+        assert_eq!(curr.get_temp(2, 1), 20.0);
+        assert!(curr.get_temp(5, 2) < 1000.0);
+    }
+
+    #[test]
+    fn test_opaque_cells_cast_shadows() {
+        let (mut world, mut engine) = radiation_world(&[
+            "#..#..L",
+            "#..#..L",
+            "#..#..L",
+        ]);
+        for _ in 0..50 {
+            engine.step(&mut world);
+        }
+
+        // The near wall takes all the heat and shades the far one.
+        let (curr, _) = world.ctx_pair();
+        assert!(curr.get_temp(3, 1) > 30.0);
+        for y in 0..3 {
+            assert_eq!(curr.get_temp(0, y), 20.0);
+        }
+    }
+
+    #[test]
+    fn test_cool_cells_do_not_radiate() {
+        let (mut world, mut engine) = radiation_world(&["#..L"]);
+        world.cell_temps.cur[3] = 250.0;
+        world.sync_all();
+        engine.step(&mut world);
+        assert_eq!(world.ctx_pair().0.get_temp(0, 0), 20.0);
+    }
+}
@@ -1,8 +1,10 @@
 ﻿use crate::physics::intent::CellIntent;
 use crate::physics::module::{Module, ModuleOutput};
-use crate::physics::util::{rand_iter_dir, NEIGHBORS_4};
+use crate::material::MaterialId;
+use crate::physics::util::{rand_iter_dir, NEIGHBORS_4, NEIGHBORS_8};
 use crate::world::{CurrCtx, PostRunCtx};
 use rand::{Rng, SeedableRng};
+use anyhow::Result;
 use rand_xoshiro::Xoshiro256PlusPlus;
 use serde_json::Value;
 use std::collections::HashMap;
@@ -14,7 +16,7 @@ pub struct ModuleReactionsBasic {
 }
 
 impl ModuleReactionsBasic {
-    pub fn new(curr: &CurrCtx<'_>, rng_seed: u64) -> Self {
+    pub fn new(_curr: &CurrCtx<'_>, rng_seed: u64) -> Self {
         Self  {
             rng_a: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
             rng_b: Xoshiro256PlusPlus::seed_from_u64(rng_seed ^ 0xBBBBBBBBBBBBBBBB),
@@ -23,11 +25,25 @@ impl ModuleReactionsBasic {
     }
 }
 
+/// Find a cell of the `catalyst` material in the 8-neighborhood of either reacting cell,
+/// excluding the reacting cells themselves.
+fn find_catalyst(curr: &CurrCtx<'_>, catalyst: MaterialId, a: (usize, usize), b: (usize, usize)) -> Option<(usize, usize)> {
+    for (cx, cy) in [a, b] {
+        for (dx, dy) in NEIGHBORS_8 {
+            let Some(cell) = curr.neighbor_cell(cx, cy, dx, dy) else { continue };
+            if (cell != a && cell != b && curr.get_mat_id(cell.0, cell.1) == catalyst) {
+                return Some(cell);
+            }
+        }
+    }
+    None
+}
+
 /// Iterate over all cells in a random order, checking for reactive neighbors.
 /// When found, rolls the chance to react and registers the intent if the roll hits.
 impl Module for ModuleReactionsBasic {
 
-    fn apply_config(&mut self, config: &HashMap<String, Value>) {}
+    fn apply_config(&mut self, _config: &HashMap<String, Value>) {}
 
     fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
 
@@ -48,40 +64,48 @@ impl Module for ModuleReactionsBasic {
             // Check neighbors for reactive materials.
             // TODO Was doing this in random order, but fixed order is SO MUCH FASTER.
             // TODO Keep an eye on, I think it might be okay as fixed order. Bias probably not noticeable?
-            for neighbor in NEIGHBORS_4 {
-                let dx = neighbor.0;
-                let dy = neighbor.1;
-                let nx = x as isize + dx;
-                let ny = y as isize + dy;
-
+            'neighbors: for neighbor in NEIGHBORS_4 {
                 // Check out of bounds.
-                if (!curr.contains(nx, ny)) { continue; }
+                let Some((nx, ny)) = curr.neighbor_cell(x, y, neighbor.0, neighbor.1) else { continue };
 
                 // Get material of this neighbor.
-                let neigh_mat = curr.get_mat_id(nx as usize, ny as usize);
-
-                // Check if this neighbor is reactive.
-                if let Some(react_id) = curr.react_db.get_reaction_by_mats(mat, neigh_mat) {
-                    if let Some(react) = curr.react_db.get(react_id) {
-
-                        // Roll dice for rate.
-                        if self.rng_b.random_range(0.0..1.0) > react.rate {
-                            continue;
-                        }
-
-                        // Reaction found. Sort which cell is a or b.
-                        let (ax, ay) = if react.in_a == mat { (x, y) } else { (nx as usize, ny as usize) };
-                        let (bx, by) = if react.in_a == mat { (nx as usize, ny as usize) } else { (x, y) };
-
-                        // Register reaction intent.
-                        intents.push(CellIntent::Reaction {
-                            cell_a: (ax, ay),
-                            cell_b: (bx, by),
-                            out_a: react.out_a,
-                            out_b: react.out_b,
-                        });
-                        break;
+                let neigh_mat = curr.get_mat_id(nx, ny);
+
+                // Check every reaction between the pair, in ID order. The first to roll its rate occurs.
+                for &react_id in curr.react_db.get_reactions_by_mats(mat, neigh_mat) {
+                    let Some(react) = curr.react_db.get(react_id) else { continue };
+
+                    // Roll dice for rate, at the mean temperature of both participants.
+                    let temp = (curr.get_temp(x, y) + curr.get_temp(nx, ny)) * 0.5;
+                    if self.rng_b.random_range(0.0..1.0) >= react.rate_at(temp) {
+                        continue;
                     }
+
+                    // Reaction found. Sort which cell is a or b.
+                    let (ax, ay) = if react.in_a == mat { (x, y) } else { (nx, ny) };
+                    let (bx, by) = if react.in_a == mat { (nx, ny) } else { (x, y) };
+
+                    // Catalyzed reactions need the catalyst nearby. It is locked along with the inputs.
+                    let catalyst = match react.catalyst {
+                        Some(catalyst) => match find_catalyst(curr, catalyst, (ax, ay), (bx, by)) {
+                            Some(cell) => Some(cell),
+                            None => continue,
+                        },
+                        None => None,
+                    };
+
+                    // Pick a weighted outcome and register reaction intent.
+                    let outcome = react.pick_outcome(self.rng_b.random_range(0.0..1.0));
+                    intents.push(CellIntent::Reaction {
+                        cell_a: (ax, ay),
+                        cell_b: (bx, by),
+                        out_a: outcome.out_a,
+                        out_b: outcome.out_b,
+                        heat_a: outcome.heat_a,
+                        heat_b: outcome.heat_b,
+                        catalyst,
+                    });
+                    break 'neighbors;
                 }
             }
         });
@@ -89,5 +113,97 @@ impl Module for ModuleReactionsBasic {
         ModuleOutput::CellIntents { intents }
     }
 
-    fn post_run(&mut self, post: &PostRunCtx<'_>, changed_cells: &[usize]) {}
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(&self.rng_a, &self.rng_b, self.checkerboard_toggle))?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        (self.rng_a, self.rng_b, self.checkerboard_toggle) = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialDb;
+    use crate::physics::engine::Engine;
+    use crate::reaction::ReactionDb;
+    use crate::world::World;
+    use std::sync::Arc;
+
+    /// A 3x1 strip of `cells` at `temp`, reacting by the reactions in `reactions`, stepped for
+    /// `ticks` ticks. Returns the final strip.
+    fn run_strip(reactions: &str, cells: [&str; 3], temp: f32, ticks: usize) -> Vec<MaterialId> {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+        let mat_db = Arc::new(mat_db);
+        let mut react_db = ReactionDb::new();
+        react_db.load_ron_str(&mat_db, reactions).unwrap();
+        let react_db = Arc::new(react_db);
+
+        let mut world = World::new(3, 1, &mat_db, &react_db);
+        {
+            let (_, mut next) = world.ctx_pair();
+            for (x, name) in cells.iter().enumerate() {
+                next.set_mat_id(x, 0, mat_db.get_id(name).unwrap());
+                next.set_temp(x, 0, temp);
+            }
+        }
+        world.swap_all();
+        world.sync_all();
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 3, 1).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleReactionsBasic::new(&curr, 42));
+        for _ in 0..ticks {
+            engine.step(&mut world);
+        }
+        world.cell_mat_ids.cur.to_vec()
+    }
+
+    #[test]
+    fn test_reaction_requires_catalyst() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+        let id = |name| mat_db.get_id(name).unwrap();
+
+        let reactions = std::fs::read_to_string("assets_test/reactions_test_catalyst.ron").unwrap();
+        let strip = |third| run_strip(&reactions, ["base:water", "base:ash", third], 20.0, 100);
+
+        // Without copper nearby, water and ash never react.
+        assert_eq!(strip("base:stone"), vec![id("base:water"), id("base:ash"), id("base:stone")]);
+
+        // With copper next to either input, they react and the copper remains.
+        assert_eq!(strip("base:copper"), vec![id("base:plant"), id("base:plant"), id("base:copper")]);
+    }
+
+    #[test]
+    fn test_reaction_never_fires_outside_temp_window() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+        let id = |name| mat_db.get_id(name).unwrap();
+
+        // A certain reaction, but only below freezing.
+        let reactions = r#"{
+            "base:water+ash=plant+plant": (
+                in_a: "base:water",
+                in_b: "base:ash",
+                out_a: "base:plant",
+                out_b: "base:plant",
+                rate: 1.0,
+                max_temp: 0.0,
+            ),
+        }"#;
+
+        // Inside the window it fires on the first tick.
+        let strip = run_strip(reactions, ["base:water", "base:ash", "base:stone"], -10.0, 1);
+        assert_eq!(strip, vec![id("base:plant"), id("base:plant"), id("base:stone")]);
+
+        // Outside it, a zero rate never fires, whatever the roll.
+        let strip = run_strip(reactions, ["base:water", "base:ash", "base:stone"], 20.0, 10_000);
+        assert_eq!(strip, vec![id("base:water"), id("base:ash"), id("base:stone")]);
+    }
 }
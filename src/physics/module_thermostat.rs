@@ -0,0 +1,110 @@
+use crate::physics::module::{Module, ModuleOutput};
+use crate::world::{CurrCtx, PostRunCtx};
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Drives thermostatic sources toward their target temperature. A cell is a source if its
+/// material has a thermostat, or if it was marked with `World::set_source`, which takes
+/// priority. Each tick a source closes `strength` of the gap between its temperature and
+/// the target, so sources with a low strength can still be overwhelmed by their surroundings.
+pub struct ModuleThermostat {}
+
+impl ModuleThermostat {
+    pub fn new(_curr: &CurrCtx<'_>) -> Self {
+        Self {}
+    }
+}
+
+impl Module for ModuleThermostat {
+
+    fn apply_config(&mut self, _config: &HashMap<String, Value>) {}
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+        let temps = curr.get_temps();
+        let mat_ids = curr.get_mat_ids();
+
+        let mut delta_temp = vec![0.0; curr.w * curr.h];
+
+        for (i, delta) in delta_temp.iter_mut().enumerate() {
+            if let Some(source) = curr.mat_db.thermostat_of(mat_ids[i]) {
+                *delta = (source.temp - temps[i]) * source.strength;
+            }
+        }
+
+        // Marked cells override their material's thermostat.
+        for (&i, source) in curr.cell_sources {
+            delta_temp[i] = (source.temp - temps[i]) * source.strength;
+        }
+
+        ModuleOutput::DeltaTemp { delta_temp }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn load_state(&mut self, _bytes: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{MaterialDb, Thermostat};
+    use crate::physics::command::{Region, WorldCommand};
+    use crate::physics::engine::Engine;
+    use crate::reaction::ReactionDb;
+    use crate::world::World;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_thermostat_material_and_marked_cell() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "base:air": (),
+                "test:rock": (),
+                "test:forge": ( thermostat_temp: 1000.0, thermostat_strength: 0.5 ),
+            }
+        "#).unwrap();
+        let mat_db = Arc::new(mat_db);
+        let react_db = Arc::new(ReactionDb::new());
+
+        // Forge, rock, rock.
+        let mut world = World::new(3, 1, &mat_db, &react_db);
+        {
+            let (_, mut next) = world.ctx_pair();
+            next.set_mat_id(0, 0, mat_db.get_id("test:forge").unwrap());
+            next.set_mat_id(1, 0, mat_db.get_id("test:rock").unwrap());
+            next.set_mat_id(2, 0, mat_db.get_id("test:rock").unwrap());
+        }
+        world.swap_all();
+        world.sync_all();
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 3, 1).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleThermostat::new(&curr));
+
+        // Mark the last rock as a full-strength cryo source.
+        engine.queue_command(WorldCommand::SetSource {
+            region: Region::Cell { x: 2, y: 0 },
+            source: Some(Thermostat { temp: -50.0, strength: 1.0 }),
+        });
+
+        // The forge closes half the gap each tick, plain rock is untouched, and the marked cell snaps to its target.
+        engine.step(&mut world);
+        assert_eq!(world.cell_temps.cur, vec![500.0, 0.0, -50.0]);
+        engine.step(&mut world);
+        assert_eq!(world.cell_temps.cur, vec![750.0, 0.0, -50.0]);
+
+        // Unmarked cells are no longer held.
+        world.set_source(2, 0, None);
+        world.cell_temps.cur[2] = 20.0;
+        engine.step(&mut world);
+        assert_eq!(world.cell_temps.cur[2], 20.0);
+    }
+}
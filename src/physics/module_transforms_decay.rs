@@ -0,0 +1,113 @@
+use crate::physics::intent::CellIntent;
+use crate::physics::module::{Module, ModuleOutput};
+use crate::world::{CurrCtx, PostRunCtx};
+use rand::{Rng, SeedableRng};
+use anyhow::Result;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub struct ModuleTransformsDecay {
+    rng: Xoshiro256PlusPlus,
+}
+
+impl ModuleTransformsDecay {
+    pub fn new(_curr: &CurrCtx<'_>, rng_seed: u64) -> Self {
+        Self {
+            rng: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
+        }
+    }
+}
+
+/// Rolls each material's `decay` rules for every cell, turning cells into their decay
+/// product on their own (such as ash crumbling away). Every cell is checked every tick, so
+/// `chance` is a true per-tick probability. Each intent only touches its own cell, so cells
+/// are visited in index order.
+impl Module for ModuleTransformsDecay {
+
+    fn apply_config(&mut self, _config: &HashMap<String, Value>) {}
+
+    fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
+
+        let mut intents = vec![];
+
+        for y in 0..curr.h {
+            for x in 0..curr.w {
+                let Some(mat) = curr.mat_db.get(curr.get_mat_id(x, y)) else { continue };
+                if (mat.decay.is_empty()) { continue; }
+
+                let age = curr.get_age(x, y);
+                for rule in &mat.decay {
+                    if (age < rule.min_age) { continue; }
+                    if self.rng.random_range(0.0..1.0) < rule.chance {
+                        intents.push(CellIntent::Transform { cell: (x, y), out: rule.out_id });
+                        break;
+                    }
+                }
+            }
+        }
+
+        ModuleOutput::CellIntents { intents }
+    }
+
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&self.rng)?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        self.rng = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialDb;
+    use crate::physics::engine::Engine;
+    use crate::reaction::ReactionDb;
+    use crate::world::World;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_decay_waits_for_min_age() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "base:air": (),
+                "test:ash": ( decay: [ ( out_name: "base:air", chance: 1.0, min_age: 5 ) ] ),
+            }
+        "#).unwrap();
+        let mat_db = Arc::new(mat_db);
+        let react_db = Arc::new(ReactionDb::new());
+        let ash = mat_db.get_id("test:ash").unwrap();
+        let air = mat_db.get_id("base:air").unwrap();
+
+        let mut world = World::new(2, 1, &mat_db, &react_db);
+        {
+            let (_, mut next) = world.ctx_pair();
+            next.set_mat_id(0, 0, ash);
+            next.set_mat_id(1, 0, air);
+        }
+        world.swap_all();
+        world.sync_all();
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 2, 1).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleTransformsDecay::new(&curr, 42));
+
+        // Ash ages one tick per step, and decays on the first step it is old enough.
+        for _ in 0..5 {
+            engine.step(&mut world);
+        }
+        assert_eq!(world.cell_mat_ids.cur[0], ash);
+        assert_eq!(world.cell_ages.cur[0], 5);
+
+        engine.step(&mut world);
+        assert_eq!(world.cell_mat_ids.cur[0], air);
+        assert_eq!(world.cell_ages.cur[0], 0);
+        assert_eq!(world.cell_ages.cur[1], 6);
+    }
+}
@@ -1,8 +1,10 @@
-﻿use crate::physics::intent::CellIntent;
+﻿use crate::material::MaterialId;
+use crate::physics::intent::CellIntent;
 use crate::physics::module::{Module, ModuleOutput};
 use crate::physics::util::{rand_iter_dir};
 use crate::world::{CurrCtx, PostRunCtx};
 use rand::{SeedableRng};
+use anyhow::Result;
 use rand_xoshiro::Xoshiro256PlusPlus;
 use serde_json::Value;
 use std::collections::HashMap;
@@ -13,7 +15,7 @@ pub struct ModuleTransformsThermal {
 }
 
 impl ModuleTransformsThermal {
-    pub fn new(curr: &CurrCtx<'_>, rng_seed: u64) -> Self {
+    pub fn new(_curr: &CurrCtx<'_>, rng_seed: u64) -> Self {
         Self {
             rng: Xoshiro256PlusPlus::seed_from_u64(rng_seed),
             checkerboard_toggle: false,
@@ -21,11 +23,25 @@ impl ModuleTransformsThermal {
     }
 }
 
+/// Move a cell's temperature past a transform threshold into its latent heat store, and
+/// transform it once the store holds `latent_heat`. `sign` is 1.0 for hot transforms and -1.0
+/// for cold ones, and `excess` is how far past the threshold the cell is. Any heat beyond
+/// what the transform needs is left in the transformed cell's temperature.
+fn phase_change(cell: (usize, usize), out: MaterialId, sign: f32, excess: f32, latent: f32, latent_heat: f32) -> CellIntent {
+    let stored = sign * latent + excess;
+    if (stored >= latent_heat) {
+        CellIntent::LatentHeat { cell, delta_temp: latent - sign * latent_heat, latent: 0.0, out: Some(out) }
+    } else {
+        CellIntent::LatentHeat { cell, delta_temp: -sign * excess, latent: sign * stored, out: None }
+    }
+}
+
 /// Iterate over all cells in a random order, checking for temperature-based material
-/// changes (such as melting).
+/// changes (such as melting). Materials with latent heat hold at their transform temperature
+/// until enough heat has been stored, which may take several ticks.
 impl Module for ModuleTransformsThermal {
 
-    fn apply_config(&mut self, config: &HashMap<String, Value>) {}
+    fn apply_config(&mut self, _config: &HashMap<String, Value>) {}
 
     fn run(&mut self, curr: &CurrCtx<'_>) -> ModuleOutput {
 
@@ -42,20 +58,36 @@ impl Module for ModuleTransformsThermal {
 
             let id = curr.get_mat_id(x, y);
             if let Some(mat) = curr.mat_db.get(id) {
+                let temp = curr.get_temp(x, y);
+                let latent = curr.get_latent(x, y);
+
+                // Give back latent heat stored toward a transform the cell has moved away from,
+                // such as partially melted ice cooling down again.
+                if (latent > 0.0 && temp < mat.transform_hot_temp) {
+                    let take = latent.min(mat.transform_hot_temp - temp);
+                    intents.push(CellIntent::LatentHeat { cell: (x, y), delta_temp: take, latent: latent - take, out: None });
+                    return;
+                }
+                if (latent < 0.0 && temp > mat.transform_cold_temp) {
+                    let take = (-latent).min(temp - mat.transform_cold_temp);
+                    intents.push(CellIntent::LatentHeat { cell: (x, y), delta_temp: -take, latent: latent + take, out: None });
+                    return;
+                }
 
                 // Check cold transform.
                 if let Some(cold_mat_id) = mat.transform_cold_mat_id {
-                    if (curr.get_temp(x, y) < mat.transform_cold_temp) {
-                        intents.push(CellIntent::Transform { cell: (x, y), out: cold_mat_id });
+                    if (temp < mat.transform_cold_temp) {
+                        let excess = mat.transform_cold_temp - temp;
+                        intents.push(phase_change((x, y), cold_mat_id, -1.0, excess, latent, mat.transform_cold_latent_heat));
                         return;
                     }
                 }
 
                 // Check hot transform.
                 if let Some(hot_mat_id) = mat.transform_hot_mat_id {
-                    if (curr.get_temp(x, y) > mat.transform_hot_temp) {
-                        intents.push(CellIntent::Transform { cell: (x, y), out: hot_mat_id });
-                        return;
+                    if (temp > mat.transform_hot_temp) {
+                        let excess = temp - mat.transform_hot_temp;
+                        intents.push(phase_change((x, y), hot_mat_id, 1.0, excess, latent, mat.transform_hot_latent_heat));
                     }
                 }
             }
@@ -64,5 +96,96 @@ impl Module for ModuleTransformsThermal {
         ModuleOutput::CellIntents { intents }
     }
 
-    fn post_run(&mut self, post: &PostRunCtx<'_>, changed_cells: &[usize]) {}
+    fn post_run(&mut self, _post: &PostRunCtx<'_>, _changed_cells: &[usize]) {}
+
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(&self.rng, self.checkerboard_toggle))?)
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        (self.rng, self.checkerboard_toggle) = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialDb;
+    use crate::physics::engine::Engine;
+    use crate::reaction::ReactionDb;
+    use crate::world::World;
+    use std::sync::Arc;
+
+    /// A single cell of `mat` at `temp`, with an engine running only thermal transforms.
+    fn single_cell(mat: &str, temp: f32) -> (World, Engine, Arc<MaterialDb>) {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"
+            {
+                "base:air": (),
+                "test:ice": ( transform_hot_mat_name: "test:water", transform_hot_temp: 0.0, transform_hot_latent_heat: 80.0 ),
+                "test:water": ( transform_cold_mat_name: "test:ice", transform_cold_temp: 0.0, transform_cold_latent_heat: 80.0 ),
+            }
+        "#).unwrap();
+        let mat_db = Arc::new(mat_db);
+        let react_db = Arc::new(ReactionDb::new());
+
+        let mut world = World::new(1, 1, &mat_db, &react_db);
+        {
+            let (_, mut next) = world.ctx_pair();
+            next.set_mat_id(0, 0, mat_db.get_id(mat).unwrap());
+            next.set_temp(0, 0, temp);
+        }
+        world.swap_all();
+        world.sync_all();
+
+        let mut engine = Engine::new(HashMap::new(), &world.mat_db, 1, 1).unwrap();
+        let (curr, _) = world.ctx_pair();
+        engine.add(ModuleTransformsThermal::new(&curr, 42));
+        (world, engine, mat_db)
+    }
+
+    /// Step until the cell has been checked once. Each cell is only checked every other tick.
+    fn step_checked(world: &mut World, engine: &mut Engine) {
+        engine.step(world);
+        engine.step(world);
+    }
+
+    #[test]
+    fn test_latent_heat_accumulates() {
+        let (mut world, mut engine, mat_db) = single_cell("test:ice", 50.0);
+        let ice = mat_db.get_id("test:ice").unwrap();
+        let water = mat_db.get_id("test:water").unwrap();
+
+        // Heat above the melting point is stored, holding the ice at 0°.
+        step_checked(&mut world, &mut engine);
+        assert_eq!(world.cell_mat_ids.cur[0], ice);
+        assert_eq!(world.cell_temps.cur[0], 0.0);
+        assert_eq!(world.cell_latent.cur[0], 50.0);
+
+        // Once the store is full, the ice melts, keeping the heat it didn't need.
+        world.cell_temps.cur[0] = 40.0;
+        step_checked(&mut world, &mut engine);
+        assert_eq!(world.cell_mat_ids.cur[0], water);
+        assert_eq!(world.cell_temps.cur[0], 10.0);
+        assert_eq!(world.cell_latent.cur[0], 0.0);
+    }
+
+    #[test]
+    fn test_latent_heat_released_and_returned() {
+        let (mut world, mut engine, mat_db) = single_cell("test:water", -30.0);
+        let water = mat_db.get_id("test:water").unwrap();
+
+        // Freezing water releases latent heat, warming back to 0°.
+        step_checked(&mut world, &mut engine);
+        assert_eq!(world.cell_mat_ids.cur[0], water);
+        assert_eq!(world.cell_temps.cur[0], 0.0);
+        assert_eq!(world.cell_latent.cur[0], -30.0);
+
+        // Warming partially frozen water takes the released heat back first.
+        world.cell_temps.cur[0] = 20.0;
+        step_checked(&mut world, &mut engine);
+        assert_eq!(world.cell_temps.cur[0], 0.0);
+        assert_eq!(world.cell_latent.cur[0], -10.0);
+    }
 }
@@ -1,4 +1,12 @@
 ﻿use rand::Rng;
+#[cfg(test)]
+use crate::material::MaterialDb;
+#[cfg(test)]
+use crate::reaction::ReactionDb;
+#[cfg(test)]
+use crate::world::World;
+#[cfg(test)]
+use std::sync::Arc;
 
 pub const NEIGHBORS_8: [(isize, isize); 8] = [
     (-1, -1), (0, -1), (1, -1),
@@ -78,6 +86,63 @@ where
     }
 }
 
+/// Build a world for tests from rows of characters. `materials` is a material database in
+/// RON, and `cell` gives the material name and starting temperature for each character.
+#[cfg(test)]
+pub fn world_from_rows<'a>(rows: &[&str], materials: &str, cell: impl Fn(char) -> (&'a str, f32)) -> (World, Arc<MaterialDb>) {
+    let mut mat_db = MaterialDb::new();
+    mat_db.load_ron_str(materials).unwrap();
+    let mat_db = Arc::new(mat_db);
+    let react_db = Arc::new(ReactionDb::new());
+
+    let (w, h) = (rows[0].len(), rows.len());
+    let mut world = World::new(w, h, &mat_db, &react_db);
+    {
+        let (_, mut next) = world.ctx_pair();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let (name, temp) = cell(c);
+                next.set_mat_id(x, y, mat_db.get_id(name).unwrap());
+                next.set_temp(x, y, temp);
+            }
+        }
+    }
+    world.swap_all();
+    world.sync_all();
+    (world, mat_db)
+}
+
+/// Characters that `mat_name` gives a material for.
+#[cfg(test)]
+const TEST_CHARS: [char; 8] = ['.', '#', 's', 'w', 'd', 'x', 'g', 'h'];
+
+/// Material drawn by a character in test rows: '#' (rock), 's' (sand), 'w' (water), 'd'
+/// (dirt), 'x' (explosive), 'g' (gas explosive), 'h' (hard explosive), or else '.' (air).
+#[cfg(test)]
+pub fn mat_name(c: char) -> &'static str {
+    match c {
+        '#' => "test:rock",
+        's' => "test:sand",
+        'w' => "test:water",
+        'd' => "test:dirt",
+        'x' => "test:tnt",
+        'g' => "test:gas_bomb",
+        'h' => "test:hard_bomb",
+        _ => "base:air",
+    }
+}
+
+/// Row `y` of the world as the characters `mat_name` draws it with.
+#[cfg(test)]
+pub fn row_string(world: &World, mat_db: &MaterialDb, y: usize) -> String {
+    (0..world.w)
+        .map(|x| {
+            let id = world.cell_mat_ids.cur[y * world.w + x];
+            TEST_CHARS.into_iter().find(|&c| mat_db.get_id(mat_name(c)) == Some(id)).unwrap()
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,21 +1,91 @@
 ﻿use std::collections::HashMap;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use anyhow::Result;
 use ron::de::from_str;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use crate::hash::StableHasher;
 use crate::material::{MaterialDb, MaterialId};
+use crate::world::KELVIN;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ReactionId(pub u16);
 
-#[derive(Clone, Debug)]
+/// Default temperature at which a reaction's `rate` applies, when it has an activation temperature.
+const DEFAULT_REF_TEMP: f32 = 20.0;
+
+fn default_ref_temp() -> f32 { DEFAULT_REF_TEMP }
+fn default_min_temp() -> f32 { f32::NEG_INFINITY }
+fn default_max_temp() -> f32 { f32::INFINITY }
+fn default_weight() -> f32 { 1.0 }
+
+/// One possible result of a reaction.
+#[derive(Serialize, Clone, Debug)]
+pub struct ReactionOutcome {
+    pub out_a: MaterialId,
+    pub out_b: MaterialId,
+    pub heat_a: f32,
+    pub heat_b: f32,
+    /// Chance of this outcome when the reaction occurs. Outcome weights sum to 1.
+    pub weight: f32,
+}
+
+#[derive(Serialize, Clone, Debug)]
 pub struct Reaction {
     pub name: String,
     pub in_a: MaterialId,
     pub in_b: MaterialId,
-    pub out_a: MaterialId,
-    pub out_b: MaterialId,
+    pub outcomes: Vec<ReactionOutcome>,
+    /// Material that must be in the 8-neighborhood of either input. It is not consumed.
+    pub catalyst: Option<MaterialId>,
     pub rate: f32,
+    pub min_temp: f32,
+    pub max_temp: f32,
+    pub activation_temp: f32,
+    pub ref_temp: f32,
+}
+
+impl Reaction {
+    /// Chance per tick of the reaction occurring when its participants have the given mean
+    /// temperature. Zero outside the `min_temp`/`max_temp` window. With an activation
+    /// temperature, the rate follows the Arrhenius equation, equal to `rate` at `ref_temp`.
+    pub fn rate_at(&self, temp: f32) -> f32 {
+        if (temp < self.min_temp || temp > self.max_temp) {
+            return 0.0;
+        }
+        if (self.activation_temp == 0.0) {
+            return self.rate;
+        }
+        let t = (temp + KELVIN).max(1.0);
+        let t_ref = self.ref_temp + KELVIN;
+        (self.rate * (self.activation_temp * (1.0 / t_ref - 1.0 / t)).exp()).min(1.0)
+    }
+
+    /// Pick an outcome from a uniform `roll` in `0.0..1.0`, according to outcome weights.
+    pub fn pick_outcome(&self, roll: f32) -> &ReactionOutcome {
+        let mut acc = 0.0;
+        for outcome in &self.outcomes {
+            acc += outcome.weight;
+            if (roll < acc) {
+                return outcome;
+            }
+        }
+        // Weights may not sum to exactly 1 due to rounding.
+        self.outcomes.last().expect("reaction must have an outcome")
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ReactionOutcomeRef {
+    pub out_a: String,
+    pub out_b: String,
+    #[serde(default)]
+    pub heat_a: f32,
+    #[serde(default)]
+    pub heat_b: f32,
+    /// Relative weight. Normalized across the reaction's outcomes when loaded.
+    #[serde(default = "default_weight")]
+    pub weight: f32,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -24,16 +94,51 @@ pub struct ReactionRef {
     pub name: String,
     pub in_a: String,
     pub in_b: String,
+    #[serde(default)]
     pub out_a: String,
+    #[serde(default)]
     pub out_b: String,
     pub rate: f32,
+
+    /// Weighted outcome table, used instead of `out_a`/`out_b`/`heat_a`/`heat_b` when a
+    /// reaction can produce different products.
+    #[serde(default)]
+    pub outcomes: Vec<ReactionOutcomeRef>,
+
+    /// Material required next to either input (including diagonals) for the reaction to occur.
+    /// Empty for no catalyst.
+    #[serde(default)]
+    pub catalyst: String,
+
+    /// Temperature added to the `out_a`/`out_b` cells when the reaction occurs. Positive for
+    /// exothermic reactions, negative for endothermic ones.
+    #[serde(default)]
+    pub heat_a: f32,
+    #[serde(default)]
+    pub heat_b: f32,
+
+    /// Mean temperature of the two inputs must be within this window for the reaction to occur.
+    #[serde(default = "default_min_temp")]
+    pub min_temp: f32,
+    #[serde(default = "default_max_temp")]
+    pub max_temp: f32,
+
+    /// Activation energy divided by the gas constant, in Kelvin. Zero disables temperature scaling.
+    #[serde(default)]
+    pub activation_temp: f32,
+    /// Temperature at which `rate` applies when temperature scaling is enabled.
+    #[serde(default = "default_ref_temp")]
+    pub ref_temp: f32,
 }
 
+#[derive(Default)]
 pub struct ReactionDb {
     defs: Vec<Reaction>,
     by_name: HashMap<String, ReactionId>,
     total_material_count: usize,
-    lookup: Vec<Option<ReactionId>>,
+
+    /// Candidate reactions for each ordered material pair, in ID order.
+    lookup: Vec<Vec<ReactionId>>,
 }
 
 impl ReactionDb {
@@ -50,26 +155,23 @@ impl ReactionDb {
         (a.0 as usize * self.total_material_count) + (b.0 as usize)
     }
 
-    pub fn get_reaction_by_mats(&self, a: MaterialId, b: MaterialId) -> Option<ReactionId> {
-        self.lookup[self.calc_lookup_index(a, b)]
+    /// Every reaction between the two materials, in either order. Empty if they don't react.
+    pub fn get_reactions_by_mats(&self, a: MaterialId, b: MaterialId) -> &[ReactionId] {
+        &self.lookup[self.calc_lookup_index(a, b)]
     }
 
     /// Insert a new reaction
     fn insert(&mut self, m: Reaction) -> ReactionId {
         let name = m.name.clone();
 
-        // If there is a reaction with same materials, panic.
-        if let Some(id) = self.get_reaction_by_mats(m.in_a, m.in_b) {
-            let existing = &self.defs[id.0 as usize];
-            panic!("tried to insert reaction {}, but there is already a reaction using these two materials ({})", name, existing.name);
-        }
-
         let id = ReactionId(self.defs.len() as u16);
         self.by_name.insert(name, id);
         let index_a = self.calc_lookup_index(m.in_a, m.in_b);
         let index_b = self.calc_lookup_index(m.in_b, m.in_a);
-        self.lookup[index_a] = Some(id);
-        self.lookup[index_b] = Some(id);
+        self.lookup[index_a].push(id);
+        if (index_b != index_a) {
+            self.lookup[index_b].push(id);
+        }
         self.defs.push(m);
         id
     }
@@ -82,16 +184,34 @@ impl ReactionDb {
         self.defs.get(id.0 as usize)
     }
 
+    /// Stable hash of every reaction definition, in ID order. See `MaterialDb::content_hash`.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.total_material_count.hash(&mut hasher);
+        self.defs.len().hash(&mut hasher);
+        for react in &self.defs {
+            ron::to_string(react).expect("reaction must serialize").hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
     pub fn load_ron_file(&mut self, material_db: &MaterialDb, path: &str) -> Result<()> {
+        let text = fs::read_to_string(path)?;
+        self.load_ron_str(material_db, &text)
+    }
+
+    pub fn load_ron_str(&mut self, material_db: &MaterialDb, text: &str) -> Result<()> {
         // Setup from MaterialDB
         self.total_material_count = material_db.get_mat_count();
-        self.lookup = vec![None; self.total_material_count * self.total_material_count];
+        self.lookup = vec![vec![]; self.total_material_count * self.total_material_count];
 
-        // Load from file
-        let text = fs::read_to_string(path)?;
-        let mut map: HashMap<String, ReactionRef> = from_str(&text)?;
+        let map: HashMap<String, ReactionRef> = from_str(text)?;
+
+        // Sort by name so IDs are assigned in a stable order (see `MaterialDb::load_ron_str`).
+        let mut entries: Vec<(String, ReactionRef)> = map.into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
 
-        for (name, react_ref) in map.drain() {
+        for (name, react_ref) in entries {
 
             // Panic if any material reference is invalid.
             if material_db.get_id(&react_ref.in_a).is_none() {
@@ -102,13 +222,56 @@ impl ReactionDb {
                 panic!("Invalid material reference: Reaction '{}' references missing in_b material '{}'",
                        name, react_ref.in_b);
             }
-            if material_db.get_id(&react_ref.out_a).is_none() {
-                panic!("Invalid material reference: Reaction '{}' references missing out_a material '{}'",
-                       name, react_ref.out_a);
+
+            // A reaction either has a single outcome given inline, or an outcome table.
+            let outcome_refs = if react_ref.outcomes.is_empty() {
+                vec![ReactionOutcomeRef {
+                    out_a: react_ref.out_a.clone(),
+                    out_b: react_ref.out_b.clone(),
+                    heat_a: react_ref.heat_a,
+                    heat_b: react_ref.heat_b,
+                    weight: 1.0,
+                }]
+            } else {
+                if (!react_ref.out_a.is_empty() || !react_ref.out_b.is_empty()) {
+                    panic!("Invalid reaction configuration: Reaction '{}' has both out_a/out_b and outcomes", name);
+                }
+                react_ref.outcomes.clone()
+            };
+
+            for outcome in &outcome_refs {
+                if material_db.get_id(&outcome.out_a).is_none() {
+                    panic!("Invalid material reference: Reaction '{}' references missing out_a material '{}'",
+                           name, outcome.out_a);
+                }
+                if material_db.get_id(&outcome.out_b).is_none() {
+                    panic!("Invalid material reference: Reaction '{}' references missing out_b material '{}'",
+                           name, outcome.out_b);
+                }
+            }
+
+            if (!react_ref.catalyst.is_empty() && material_db.get_id(&react_ref.catalyst).is_none()) {
+                panic!("Invalid material reference: Reaction '{}' references missing catalyst material '{}'",
+                       name, react_ref.catalyst);
+            }
+
+            // Panic if outcome weights can't be normalized.
+            let total_weight: f32 = outcome_refs.iter().map(|o| o.weight).sum();
+            if (outcome_refs.iter().any(|o| o.weight < 0.0) || total_weight <= 0.0) {
+                panic!("Invalid reaction configuration: Reaction '{}' has negative or zero outcome weights", name);
+            }
+
+            // Panic if the temperature window is empty.
+            if (react_ref.min_temp > react_ref.max_temp) {
+                panic!("Invalid reaction configuration: Reaction '{}' has min_temp ({}) higher than max_temp ({})",
+                       name, react_ref.min_temp, react_ref.max_temp);
             }
-            if material_db.get_id(&react_ref.out_b).is_none() {
-                panic!("Invalid material reference: Reaction '{}' references missing out_b material '{}'",
-                       name, react_ref.out_b);
+
+            // Panic if the Arrhenius scaling would divide by zero or go through a negative
+            // absolute temperature.
+            if (!react_ref.activation_temp.is_finite() || !react_ref.ref_temp.is_finite() || react_ref.ref_temp + KELVIN <= 0.0) {
+                panic!("Invalid reaction configuration: Reaction '{}' has invalid activation_temp ({}) or ref_temp ({})",
+                       name, react_ref.activation_temp, react_ref.ref_temp);
             }
 
             // Do not save if rate is zero or negative. Zero-rate reactions will
@@ -120,9 +283,21 @@ impl ReactionDb {
                 name,
                 in_a: material_db.get_id(&react_ref.in_a).unwrap(),
                 in_b: material_db.get_id(&react_ref.in_b).unwrap(),
-                out_a: material_db.get_id(&react_ref.out_a).unwrap(),
-                out_b: material_db.get_id(&react_ref.out_b).unwrap(),
+                outcomes: outcome_refs.iter()
+                    .map(|o| ReactionOutcome {
+                        out_a: material_db.get_id(&o.out_a).unwrap(),
+                        out_b: material_db.get_id(&o.out_b).unwrap(),
+                        heat_a: o.heat_a,
+                        heat_b: o.heat_b,
+                        weight: o.weight / total_weight,
+                    })
+                    .collect(),
+                catalyst: material_db.get_id(&react_ref.catalyst),
                 rate: react_ref.rate,
+                min_temp: react_ref.min_temp,
+                max_temp: react_ref.max_temp,
+                activation_temp: react_ref.activation_temp,
+                ref_temp: react_ref.ref_temp,
             };
             self.insert(react);
         }
@@ -140,7 +315,7 @@ mod tests {
         let mut mat_db = MaterialDb::new();
         mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
 
-        let mut react_db = ReactionDb::new();;
+        let mut react_db = ReactionDb::new();
         react_db.load_ron_file(&mat_db, "assets_test/reactions_test.ron").unwrap();
 
         assert_eq!(react_db.total_material_count, 12); // Ensure we got the right number of materials.
@@ -155,12 +330,25 @@ mod tests {
             // Ensure reactions link to materials correctly.
             assert_eq!(react_plant_growth.in_a, mat_id_plant);
             assert_eq!(react_plant_growth.in_b, mat_id_water);
-            assert_eq!(react_plant_growth.out_a, mat_id_plant);
-            assert_eq!(react_plant_growth.out_b, mat_id_plant);
+            assert_eq!(react_plant_growth.outcomes.len(), 1);
+            assert_eq!(react_plant_growth.outcomes[0].out_a, mat_id_plant);
+            assert_eq!(react_plant_growth.outcomes[0].out_b, mat_id_plant);
+            assert_eq!(react_plant_growth.outcomes[0].weight, 1.0);
 
             // Ensure reactions are saved to the lookup table in both directions.
-            assert_eq!(react_db.get_reaction_by_mats(mat_id_plant, mat_id_water), Some(*react_id_plant_growth));
-            assert_eq!(react_db.get_reaction_by_mats(mat_id_water, mat_id_plant), Some(*react_id_plant_growth));
+            assert_eq!(react_db.get_reactions_by_mats(mat_id_plant, mat_id_water), &[*react_id_plant_growth]);
+            assert_eq!(react_db.get_reactions_by_mats(mat_id_water, mat_id_plant), &[*react_id_plant_growth]);
+
+            // Heat is optional and defaults to zero.
+            assert_eq!(react_plant_growth.outcomes[0].heat_a, 0.0);
+            assert_eq!(react_plant_growth.outcomes[0].heat_b, 0.0);
+        }
+
+        {
+            let react_id_quench = react_db.get_id("base:lava+water=stone+steam").unwrap();
+            let react_quench = react_db.get(react_id_quench).unwrap();
+            assert_eq!(react_quench.outcomes[0].heat_a, -300.0);
+            assert_eq!(react_quench.outcomes[0].heat_b, 150.0);
         }
     }
 
@@ -208,6 +396,161 @@ mod tests {
         react_db.load_ron_file(&mat_db, "assets_test/reactions_test_invalid_out_b.ron").unwrap();
     }
 
+    #[test]
+    fn test_ids_assigned_in_name_order() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+        let mut react_db = ReactionDb::new();
+        react_db.load_ron_file(&mat_db, "assets_test/reactions_test.ron").unwrap();
+
+        // IDs must not depend on HashMap iteration order.
+        let names: Vec<&str> = react_db.defs.iter().map(|r| r.name.as_str()).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+        assert_eq!(react_db.get_id("base:ash+air=air+air"), Some(ReactionId(0)));
+    }
+
+    #[test]
+    fn test_content_hash_stable() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+
+        let mut react_db_a = ReactionDb::new();
+        react_db_a.load_ron_file(&mat_db, "assets_test/reactions_test.ron").unwrap();
+        let mut react_db_b = ReactionDb::new();
+        react_db_b.load_ron_file(&mat_db, "assets_test/reactions_test.ron").unwrap();
+        assert_eq!(react_db_a.content_hash(), react_db_b.content_hash());
+
+        // A different set of reactions must hash differently.
+        assert_ne!(react_db_a.content_hash(), ReactionDb::new().content_hash());
+    }
+
+    fn rate_test_reaction() -> Reaction {
+        Reaction {
+            name: "test".to_owned(),
+            in_a: MaterialId(0),
+            in_b: MaterialId(1),
+            outcomes: vec![],
+            catalyst: None,
+            rate: 0.1,
+            min_temp: default_min_temp(),
+            max_temp: default_max_temp(),
+            activation_temp: 0.0,
+            ref_temp: DEFAULT_REF_TEMP,
+        }
+    }
+
+    #[test]
+    fn test_rate_temp_window() {
+        let mut react = rate_test_reaction();
+        assert_eq!(react.rate_at(-1000.0), 0.1);
+
+        react.min_temp = 100.0;
+        react.max_temp = 200.0;
+        assert_eq!(react.rate_at(99.0), 0.0);
+        assert_eq!(react.rate_at(100.0), 0.1);
+        assert_eq!(react.rate_at(200.0), 0.1);
+        assert_eq!(react.rate_at(201.0), 0.0);
+    }
+
+    #[test]
+    fn test_rate_arrhenius_scaling() {
+        let mut react = rate_test_reaction();
+        react.activation_temp = 5000.0;
+
+        // Rate applies at the reference temperature, grows when hotter and shrinks when colder.
+        assert!((react.rate_at(DEFAULT_REF_TEMP) - 0.1).abs() < 1e-6);
+        assert!(react.rate_at(40.0) > 0.1);
+        assert!(react.rate_at(0.0) < 0.1);
+
+        // Never more than certain.
+        assert_eq!(react.rate_at(5000.0), 1.0);
+    }
+
+    #[test]
+    fn test_multiple_reactions_and_outcomes() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+        let mut react_db = ReactionDb::new();
+        react_db.load_ron_file(&mat_db, "assets_test/reactions_test_multiple.ron").unwrap();
+
+        let mat_id_lava = mat_db.get_id("base:lava").unwrap();
+        let mat_id_water = mat_db.get_id("base:water").unwrap();
+        let mat_id_stone = mat_db.get_id("base:stone").unwrap();
+        let mat_id_ice = mat_db.get_id("base:ice").unwrap();
+
+        // Both reactions on the same pair are candidates, in ID (name) order.
+        let quench = react_db.get_id("base:lava+water=quench").unwrap();
+        let splash = react_db.get_id("base:water+lava=splash").unwrap();
+        assert_eq!(react_db.get_reactions_by_mats(mat_id_lava, mat_id_water), &[quench, splash]);
+        assert_eq!(react_db.get_reactions_by_mats(mat_id_water, mat_id_lava), &[quench, splash]);
+
+        // Outcome weights are normalized and picked by cumulative weight.
+        let react = react_db.get(quench).unwrap();
+        assert_eq!(react.outcomes[0].weight, 0.7);
+        assert!((react.outcomes[1].weight - 0.3).abs() < 1e-6);
+        assert_eq!(react.pick_outcome(0.0).out_a, mat_id_stone);
+        assert_eq!(react.pick_outcome(0.69).out_a, mat_id_stone);
+        assert_eq!(react.pick_outcome(0.71).out_a, mat_id_ice);
+        assert_eq!(react.pick_outcome(0.9999).out_a, mat_id_ice);
+    }
+
+    #[test]
+    fn test_catalyst() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+        let mut react_db = ReactionDb::new();
+        react_db.load_ron_file(&mat_db, "assets_test/reactions_test_catalyst.ron").unwrap();
+
+        let catalyzed = react_db.get_id("base:water+ash=plant+plant").unwrap();
+        let plain = react_db.get_id("base:plant+water=plant+plant").unwrap();
+        assert_eq!(react_db.get(catalyzed).unwrap().catalyst, mat_db.get_id("base:copper"));
+        assert_eq!(react_db.get(plain).unwrap().catalyst, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid material reference")]
+    fn test_invalid_catalyst() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+        let mut react_db = ReactionDb::new();
+        react_db.load_ron_str(&mat_db, r#"
+            {
+                "base:water+ash=plant+plant": (
+                    in_a: "base:water",
+                    in_b: "base:ash",
+                    out_a: "base:plant",
+                    out_b: "base:plant",
+                    rate: 0.5,
+                    catalyst: "base:unobtainium",
+                ),
+            }
+        "#).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid reaction configuration")]
+    fn test_invalid_temp_window() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+        let mut react_db = ReactionDb::new();
+
+        // Ensure panic when min_temp is higher than max_temp.
+        react_db.load_ron_file(&mat_db, "assets_test/reactions_test_invalid_temp_window.ron").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid reaction configuration")]
+    fn test_invalid_ref_temp() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_file("assets_test/materials_test.ron").unwrap();
+        let mut react_db = ReactionDb::new();
+
+        // Ensure panic when ref_temp is at or below absolute zero.
+        react_db.load_ron_file(&mat_db, "assets_test/reactions_test_invalid_ref_temp.ron").unwrap();
+    }
+
     #[test]
     fn test_ensure_db_starts_empty() {
         let react_db = ReactionDb::new();
@@ -0,0 +1,99 @@
+//! Backend-independent helpers for turning world state into colors. Used by the visualizer
+//! and by the headless runner to produce identical material and thermal views.
+
+use crate::material::{Color, MaterialDb, MaterialId};
+
+/// Temperature shown as neutral gray in the thermal view.
+pub const THERMAL_VIEW_CENTER: f32 = 50.0;
+
+/// How strongly the thermal gradient is blended over material colors in the thermal view.
+pub const THERMAL_VIEW_ALPHA: f32 = 0.75;
+
+pub const COLORS_THERM_GRADIENT: [Color; 3] = [
+    Color::from_rgba(0, 96, 255, 255),
+    Color::from_rgba(115, 115, 115, 255),
+    Color::from_rgba(255, 64, 0, 255),
+];
+
+/// Generates a color from a ratio and a bundle of three colors (neg, zero, pos).
+pub fn triple_gradient_bun(ratio: f32, bundle: &[Color]) -> Color {
+    triple_gradient(ratio, bundle[0], bundle[1], bundle[2])
+}
+
+/// Generates a color from a ratio and gradient definition.
+pub fn triple_gradient(ratio: f32, neg: Color, zero: Color, pos: Color) -> Color {
+    let ratio = ratio.clamp(-1.0, 1.0);
+
+    let (from, to, w) = if ratio < 0.0 {
+        (zero, neg, -ratio)
+    } else {
+        (zero, pos, ratio)
+    };
+
+    let r = from.r + (to.r - from.r) * w;
+    let g = from.g + (to.g - from.g) * w;
+    let b = from.b + (to.b - from.b) * w;
+
+    Color::new(r, g, b, 1.0)
+}
+
+/// Color of a cell in the thermal view: the material color with the heat gradient blended over it.
+/// `range` controls how much temperature variation fits into the gradient.
+pub fn thermal_view_color(mat_color: Color, temp: f32, range: f32) -> Color {
+    let t = ((temp - THERMAL_VIEW_CENTER) / range).clamp(-1.0, 1.0);
+    let therm_rgb = triple_gradient_bun(t, &COLORS_THERM_GRADIENT);
+
+    let mut out = mat_color;
+    out.r += (therm_rgb.r - out.r) * THERMAL_VIEW_ALPHA;
+    out.g += (therm_rgb.g - out.g) * THERMAL_VIEW_ALPHA;
+    out.b += (therm_rgb.b - out.b) * THERMAL_VIEW_ALPHA;
+    out
+}
+
+/// Render cells to a tightly packed RGBA8 buffer. Pass `thermal_range` to render the thermal view.
+pub fn render_rgba8(mat_ids: &[MaterialId], temps: &[f32], mat_db: &MaterialDb, thermal_range: Option<f32>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(mat_ids.len() * 4);
+    for (&id, &temp) in mat_ids.iter().zip(temps) {
+        let mut color = mat_db.get(id).map(|m| m.color).unwrap_or_default();
+        if let Some(range) = thermal_range {
+            color = thermal_view_color(color, temp, range);
+        }
+        out.extend_from_slice(&[
+            (color.r * 255.0).round() as u8,
+            (color.g * 255.0).round() as u8,
+            (color.b * 255.0).round() as u8,
+            (color.a * 255.0).round() as u8,
+        ]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triple_gradient_endpoints() {
+        let [neg, zero, pos] = COLORS_THERM_GRADIENT;
+        assert_eq!(triple_gradient_bun(-1.0, &COLORS_THERM_GRADIENT), neg);
+        assert_eq!(triple_gradient_bun(0.0, &COLORS_THERM_GRADIENT), zero);
+        assert_eq!(triple_gradient_bun(1.0, &COLORS_THERM_GRADIENT), pos);
+
+        // Ratios beyond +/- 1 are clamped.
+        assert_eq!(triple_gradient_bun(5.0, &COLORS_THERM_GRADIENT), pos);
+    }
+
+    #[test]
+    fn test_render_rgba8() {
+        let mut mat_db = MaterialDb::new();
+        mat_db.load_ron_str(r#"{ "test:red": ( color_raw: (255, 0, 0, 255) ) }"#).unwrap();
+        let id = mat_db.get_id("test:red").unwrap();
+
+        let plain = render_rgba8(&[id, id], &[0.0, 0.0], &mat_db, None);
+        assert_eq!(plain, vec![255, 0, 0, 255, 255, 0, 0, 255]);
+
+        // Very hot and very cold cells are tinted towards the gradient ends.
+        let thermal = render_rgba8(&[id, id], &[10000.0, -10000.0], &mat_db, Some(500.0));
+        assert!(thermal[2] < thermal[6]); // Cold cell is bluer.
+    }
+}
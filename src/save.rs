@@ -0,0 +1,269 @@
+//! Versioned binary save format for full world state.
+//!
+//! Layout: an 8-byte magic, a little-endian `u32` version, then a bincode-encoded `SaveData`.
+//! Cell materials are stored as indices into a material name table rather than raw
+//! `MaterialId`s, so a save stays valid if the material database is reordered or extended.
+//! The world boundary is saved along with the world size, since both are part of the world.
+//! Module state (RNGs, cached values) is stored as opaque per-module blobs, in module order.
+
+use crate::boundary::Boundary;
+use crate::material::{MaterialDb, MaterialId, Thermostat};
+use crate::physics::engine::Engine;
+use crate::reaction::ReactionDb;
+use crate::sim::Entity;
+use crate::world::World;
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::sync::Arc;
+
+pub const SAVE_MAGIC: [u8; 8] = *b"MINTSAVE";
+pub const SAVE_VERSION: u32 = 9;
+
+#[derive(Serialize, Deserialize)]
+struct SaveData {
+    w: usize,
+    h: usize,
+    tick_count: u64,
+    /// Its `void_mat` is an index into `material_names`, like `cell_mats`.
+    boundary: Boundary,
+
+    /// Material names, indexed by the values in `cell_mats` and `cell_fuel`.
+    material_names: Vec<String>,
+    cell_mats: Vec<u16>,
+    cell_fuel: Vec<u16>,
+    cell_temps: Vec<f32>,
+    cell_ages: Vec<u32>,
+    cell_latent: Vec<f32>,
+    cell_gas_mass: Vec<f32>,
+    cell_vel_x: Vec<f32>,
+    cell_vel_y: Vec<f32>,
+    entities: Vec<Entity>,
+    /// Cells marked as thermostatic sources, by cell index.
+    cell_sources: Vec<(usize, Thermostat)>,
+
+    module_states: Vec<Vec<u8>>,
+}
+
+impl World {
+    /// Save the world, plus the state of every module in `engine`, to a file.
+    pub fn save(&self, engine: &Engine, path: &str) -> Result<()> {
+        let file = File::create(path).with_context(|| format!("failed to create save file '{}'", path))?;
+        let mut writer = BufWriter::new(file);
+        self.save_to(engine, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load a world from a file, restoring module state into `engine`. The engine must be
+    /// built for the same world size, with the same modules added in the same order.
+    pub fn load(path: &str, mat_db: &Arc<MaterialDb>, react_db: &Arc<ReactionDb>, engine: &mut Engine) -> Result<World> {
+        let file = File::open(path).with_context(|| format!("failed to open save file '{}'", path))?;
+        World::load_from(&mut BufReader::new(file), mat_db, react_db, engine)
+    }
+
+    pub fn save_to<W: Write>(&self, engine: &Engine, writer: &mut W) -> Result<()> {
+        let data = SaveData {
+            w: self.w,
+            h: self.h,
+            tick_count: self.tick_count,
+            boundary: self.boundary,
+            material_names: (0..self.mat_db.get_mat_count())
+                .map(|i| self.mat_db.get(MaterialId(i as u16)).unwrap().name.clone())
+                .collect(),
+            cell_mats: self.cell_mat_ids.cur.iter().map(|id| id.0).collect(),
+            cell_fuel: self.cell_fuel.cur.iter().map(|id| id.0).collect(),
+            cell_temps: self.cell_temps.cur.clone(),
+            cell_ages: self.cell_ages.cur.clone(),
+            cell_latent: self.cell_latent.cur.clone(),
+            cell_gas_mass: self.cell_gas_mass.cur.clone(),
+            cell_vel_x: self.cell_vel_x.cur.clone(),
+            cell_vel_y: self.cell_vel_y.cur.clone(),
+            entities: self.entities.cur.clone(),
+            cell_sources: self.cell_sources.iter().map(|(&i, &source)| (i, source)).collect(),
+            module_states: engine.save_module_states()?,
+        };
+
+        writer.write_all(&SAVE_MAGIC)?;
+        writer.write_all(&SAVE_VERSION.to_le_bytes())?;
+        bincode::serialize_into(writer, &data)?;
+        Ok(())
+    }
+
+    pub fn load_from<R: Read>(reader: &mut R, mat_db: &Arc<MaterialDb>, react_db: &Arc<ReactionDb>, engine: &mut Engine) -> Result<World> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        ensure!(magic == SAVE_MAGIC, "not a mintage save file");
+
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != SAVE_VERSION {
+            bail!("unsupported save version {} (expected {})", version, SAVE_VERSION);
+        }
+
+        let data: SaveData = bincode::deserialize_from(reader)?;
+        let cell_count = data.w * data.h;
+        ensure!(data.cell_mats.len() == cell_count
+            && data.cell_fuel.len() == cell_count
+            && data.cell_temps.len() == cell_count
+            && data.cell_ages.len() == cell_count
+            && data.cell_latent.len() == cell_count
+            && data.cell_gas_mass.len() == cell_count
+            && data.cell_vel_x.len() == cell_count
+            && data.cell_vel_y.len() == cell_count
+            && data.entities.len() == cell_count, "save file cell buffers do not match world size");
+        ensure!(data.cell_sources.iter().all(|&(i, _)| i < cell_count), "save file has a source outside the world");
+        ensure!(engine.cell_count() == cell_count,
+            "engine was built for {} cells, save file has {}", engine.cell_count(), cell_count);
+
+        // Map the save's material table onto the current database.
+        let remap = data.material_names.iter()
+            .map(|name| mat_db.get_id(name).with_context(|| format!("save file references missing material '{}'", name)))
+            .collect::<Result<Vec<MaterialId>>>()?;
+
+        let mut world = World::new(data.w, data.h, mat_db, react_db);
+        world.tick_count = data.tick_count;
+        world.boundary = Boundary {
+            void_mat: remap.get(data.boundary.void_mat.0 as usize).copied().context("save file has invalid material index")?,
+            ..data.boundary
+        };
+        world.cell_mat_ids.cur = data.cell_mats.iter()
+            .map(|&i| remap.get(i as usize).copied().context("save file has invalid material index"))
+            .collect::<Result<Vec<MaterialId>>>()?;
+        world.cell_fuel.cur = data.cell_fuel.iter()
+            .map(|&i| remap.get(i as usize).copied().context("save file has invalid material index"))
+            .collect::<Result<Vec<MaterialId>>>()?;
+        world.cell_temps.cur = data.cell_temps;
+        world.cell_ages.cur = data.cell_ages;
+        world.cell_latent.cur = data.cell_latent;
+        world.cell_gas_mass.cur = data.cell_gas_mass;
+        world.cell_vel_x.cur = data.cell_vel_x;
+        world.cell_vel_y.cur = data.cell_vel_y;
+        world.entities.cur = data.entities;
+        world.cell_sources = data.cell_sources.into_iter().collect();
+        world.sync_all();
+
+        engine.load_module_states(&data.module_states)?;
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{build_world_and_engine, AssetPaths, DEFAULT_SEED};
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    fn load_dbs() -> (Arc<MaterialDb>, Arc<ReactionDb>) {
+        crate::sim::load_dbs(&AssetPaths::default()).unwrap()
+    }
+
+    /// Config with a non-default boundary, which a loaded world must restore to continue identically.
+    fn config() -> HashMap<String, Value> {
+        ron::de::from_str(r#"{ "boundary": { "west": "periodic", "east": "periodic", "north": "void", "south": "ambient", "ambient_temp": 20.0 } }"#).unwrap()
+    }
+
+    /// Fill the world with materials that keep every module busy (boiling water, steam,
+    /// lava reactions, growing and burning plants, decaying ash, exploding gunpowder), so
+    /// module RNG state matters for the outcome.
+    fn stir_world(world: &mut World, mat_db: &MaterialDb) {
+        let names = ["base:water", "base:lava", "base:plant", "base:steam", "base:air", "base:ice", "base:ash", "base:gunpowder"];
+        let temps = [150.0, 1200.0, 50.0, 200.0, 50.0, -20.0, 50.0, 50.0];
+        let (w, h) = (world.w, world.h);
+        let (_, mut next) = world.ctx_pair();
+        for y in 0..h {
+            for x in 0..w {
+                let k = (x * 7 + y * 13 + (x * y) % 5) % names.len();
+                next.set_mat_id(x, y, mat_db.get_id(names[k]).unwrap());
+                next.set_temp(x, y, temps[k]);
+            }
+        }
+        world.swap_all();
+        world.sync_all();
+    }
+
+    #[test]
+    fn test_save_load_continues_bit_identically() {
+        let (mat_db, react_db) = load_dbs();
+        let (w, h) = (96, 64);
+
+        let (mut world, mut engine) = build_world_and_engine(config(), w, h, &mat_db, &react_db, &AssetPaths::default(), DEFAULT_SEED).unwrap();
+        stir_world(&mut world, &mat_db);
+        world.set_source(10, 10, Some(Thermostat { temp: 900.0, strength: 0.25 }));
+        for _ in 0..20 {
+            engine.step(&mut world);
+        }
+
+        let mut bytes = vec![];
+        world.save_to(&engine, &mut bytes).unwrap();
+
+        // Continue the original uninterrupted.
+        for _ in 0..30 {
+            engine.step(&mut world);
+        }
+
+        // Load into a freshly built engine, whose modules start from their initial state.
+        let (_, mut loaded_engine) = build_world_and_engine(config(), w, h, &mat_db, &react_db, &AssetPaths::default(), DEFAULT_SEED).unwrap();
+        let mut loaded = World::load_from(&mut bytes.as_slice(), &mat_db, &react_db, &mut loaded_engine).unwrap();
+        assert_eq!(loaded.tick_count, 20);
+        assert_eq!(loaded.boundary, world.boundary);
+        assert_eq!(loaded.cell_sources, world.cell_sources);
+        for _ in 0..30 {
+            loaded_engine.step(&mut loaded);
+        }
+
+        assert_eq!(loaded.tick_count, world.tick_count);
+        assert_eq!(loaded.cell_mat_ids.cur, world.cell_mat_ids.cur);
+        assert_eq!(loaded.cell_ages.cur, world.cell_ages.cur);
+        assert_eq!(loaded.cell_fuel.cur, world.cell_fuel.cur);
+        let temps_a: Vec<u32> = world.cell_temps.cur.iter().map(|t| t.to_bits()).collect();
+        let temps_b: Vec<u32> = loaded.cell_temps.cur.iter().map(|t| t.to_bits()).collect();
+        assert_eq!(temps_a, temps_b);
+        let latent_a: Vec<u32> = world.cell_latent.cur.iter().map(|t| t.to_bits()).collect();
+        let latent_b: Vec<u32> = loaded.cell_latent.cur.iter().map(|t| t.to_bits()).collect();
+        assert_eq!(latent_a, latent_b);
+        let mass_a: Vec<u32> = world.cell_gas_mass.cur.iter().map(|m| m.to_bits()).collect();
+        let mass_b: Vec<u32> = loaded.cell_gas_mass.cur.iter().map(|m| m.to_bits()).collect();
+        assert_eq!(mass_a, mass_b);
+        let vel_a: Vec<u32> = world.cell_vel_x.cur.iter().chain(&world.cell_vel_y.cur).map(|v| v.to_bits()).collect();
+        let vel_b: Vec<u32> = loaded.cell_vel_x.cur.iter().chain(&loaded.cell_vel_y.cur).map(|v| v.to_bits()).collect();
+        assert_eq!(vel_a, vel_b);
+    }
+
+    #[test]
+    fn test_load_rejects_bad_magic_and_version() {
+        let (mat_db, react_db) = load_dbs();
+        let (world, mut engine) = build_world_and_engine(config(), 8, 8, &mat_db, &react_db, &AssetPaths::default(), DEFAULT_SEED).unwrap();
+
+        let mut bytes = vec![];
+        world.save_to(&engine, &mut bytes).unwrap();
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(World::load_from(&mut bad_magic.as_slice(), &mat_db, &react_db, &mut engine).is_err());
+
+        let mut bad_version = bytes.clone();
+        bad_version[8..12].copy_from_slice(&(SAVE_VERSION + 1).to_le_bytes());
+        assert!(World::load_from(&mut bad_version.as_slice(), &mat_db, &react_db, &mut engine).is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_missing_material() {
+        let (mat_db, react_db) = load_dbs();
+        let (world, _) = build_world_and_engine(config(), 8, 8, &mat_db, &react_db, &AssetPaths::default(), DEFAULT_SEED).unwrap();
+        let (_, mut engine) = build_world_and_engine(config(), 8, 8, &mat_db, &react_db, &AssetPaths::default(), DEFAULT_SEED).unwrap();
+
+        let mut bytes = vec![];
+        world.save_to(&engine, &mut bytes).unwrap();
+
+        // A database without the saved materials cannot load the file.
+        let mut small_db = MaterialDb::new();
+        small_db.load_ron_str(r#"{ "base:air": () }"#).unwrap();
+        let small_db = Arc::new(small_db);
+        let result = World::load_from(&mut bytes.as_slice(), &small_db, &react_db, &mut engine);
+        assert!(result.is_err());
+    }
+}
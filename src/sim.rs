@@ -1,19 +1,33 @@
+use crate::boundary::Boundary;
+use crate::clock::{ClockStatus, SimClock, SimControl, DEFAULT_TARGET_TPS};
 use crate::material::{MaterialDb, MaterialId};
+use crate::physics::command::WorldCommand;
 use crate::physics::engine::Engine;
-use crate::physics::module_behavior_steam::ModuleBehaviorSteam;
+use crate::physics::module_gas::ModuleGas;
+use crate::physics::module_gravity::ModuleGravity;
 use crate::physics::module_reactions_basic::ModuleReactionsBasic;
 use crate::reaction::ReactionDb;
 use crate::world::World;
+use anyhow::{ensure, Context, Result};
 use arc_swap::ArcSwap;
-use macroquad::math::{f64, u64};
-use macroquad::prelude::get_time;
 use std::{fs, mem};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::time::Instant;
 use image::GenericImageView;
 use serde_json::Value;
+use crate::physics::module_combustion::ModuleCombustion;
+use crate::physics::module_combustion_heat::ModuleCombustionHeat;
 use crate::physics::module_diffusion_thermal::ModuleDiffusionThermal;
+use crate::physics::module_explosion::ModuleExplosion;
+use crate::physics::module_fluid::ModuleFluid;
+use crate::physics::module_fluid_flow::ModuleFluidFlow;
+use crate::physics::module_pressure::ModulePressure;
+use crate::physics::module_radiation::ModuleRadiation;
+use crate::physics::module_transforms_decay::ModuleTransformsDecay;
+use crate::physics::module_thermostat::ModuleThermostat;
 use crate::physics::module_transforms_thermal::ModuleTransformsThermal;
 
 /// Generic double buffer over any T. We use it for `Vec<MaterialId>` and `Vec<Entity>`.
@@ -39,11 +53,17 @@ impl<T: Clone> DoubleBuffer<T> {
 }
 
 /// Empty placeholder for future entities class.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Entity {
 
 }
 
+impl Default for Entity {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
 impl Entity {
     pub fn empty() -> Self {
         Self { }
@@ -67,46 +87,76 @@ impl Snapshot {
     }
 }
 
-/// Stores data used by both the Sim thread and the Render thread.
+/// A message to the Sim thread. Clock controls and world edits share one channel, so either
+/// wakes the thread.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SimMessage {
+    Control(SimControl),
+    Command(WorldCommand),
+}
+
+/// Stores data used by both the Sim thread and the Render thread. The Sim thread only holds
+/// a weak handle to it, and stops once the last `Arc<Shared>` is dropped.
 pub struct Shared {
     pub current: ArcSwap<Snapshot>,
+    pub clock: ArcSwap<ClockStatus>,
     pub mat_db: Arc<MaterialDb>,
     pub react_db: Arc<ReactionDb>,
     pub tick_count: AtomicU64,
+    messages: Sender<SimMessage>,
 }
 
 impl Shared {
-    pub fn new(initial: Arc<Snapshot>, mat_db: Arc<MaterialDb>, react_db: Arc<ReactionDb>) -> Arc<Self> {
+    pub fn new(initial: Arc<Snapshot>, clock: ClockStatus, mat_db: Arc<MaterialDb>, react_db: Arc<ReactionDb>, messages: Sender<SimMessage>) -> Arc<Self> {
         Arc::new(Self {
             current: ArcSwap::new(initial),
+            clock: ArcSwap::from_pointee(clock),
             mat_db,
             react_db,
             tick_count: AtomicU64::new(0),
+            messages,
         })
     }
+
+    /// Send a clock command to the Sim thread. Ignored if the Sim thread has stopped.
+    pub fn send_control(&self, cmd: SimControl) {
+        let _ = self.messages.send(SimMessage::Control(cmd));
+    }
+
+    /// Send a world edit to the Sim thread. It is applied at the start of the next tick, or
+    /// right away while paused. Ignored if the Sim thread has stopped.
+    pub fn send_command(&self, cmd: WorldCommand) {
+        let _ = self.messages.send(SimMessage::Command(cmd));
+    }
 }
 
 /// Helper for keeping track of ticks per second.
 pub struct TpsTracker {
     last_ticks: u64,
-    last_time: f64,
+    last_time: Instant,
     recent_tps: f64,
 }
 
+impl Default for TpsTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TpsTracker {
     pub fn new() -> Self {
         Self {
             last_ticks: 0,
-            last_time: get_time(),
+            last_time: Instant::now(),
             recent_tps: 0.0,
         }
     }
 
     pub fn update(&mut self, shared: &Arc<Shared>) -> f64 {
-        let now = get_time();
+        let now = Instant::now();
         let ticks = shared.tick_count.load(Ordering::Relaxed);
 
-        let delta_time = now - self.last_time;
+        let delta_time = now.duration_since(self.last_time).as_secs_f64();
         let delta_ticks = ticks - self.last_ticks;
 
         if delta_time >= 1.0 {
@@ -119,6 +169,56 @@ impl TpsTracker {
     }
 }
 
+/// Seed used by the demo when none is given.
+pub const DEFAULT_SEED: u64 = 123456789;
+
+/// Locations of the files that define a simulation.
+#[derive(Clone, Debug)]
+pub struct AssetPaths {
+    pub config: String,
+    pub materials: String,
+    pub reactions: String,
+    /// Bitmap the world starts from. Without one, the world starts empty.
+    pub map: Option<String>,
+    pub map_key: String,
+}
+
+impl Default for AssetPaths {
+    fn default() -> Self {
+        Self {
+            config: "assets/config.ron".to_owned(),
+            materials: "assets/materials_base.ron".to_owned(),
+            reactions: "assets/reactions_base.ron".to_owned(),
+            map: Some("assets/map.png".to_owned()),
+            map_key: "assets/map_key.ron".to_owned(),
+        }
+    }
+}
+
+/// Load config from a RON file.
+pub fn load_config(path: &str) -> Result<HashMap<String, Value>> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Missing config: {}", path))?;
+    Ok(ron::de::from_str(&contents)?)
+}
+
+/// Read the world size in cells from config.
+pub fn world_size(config: &HashMap<String, Value>) -> Result<(usize, usize)> {
+    let w = config.get("world_width").context("Missing config: world_width")?
+        .as_u64().context("Invalid config: world_width must be u64")? as usize;
+    let h = config.get("world_height").context("Missing config: world_height")?
+        .as_u64().context("Invalid config: world_height must be u64")? as usize;
+    Ok((w, h))
+}
+
+/// Load the material and reaction databases.
+pub fn load_dbs(paths: &AssetPaths) -> Result<(Arc<MaterialDb>, Arc<ReactionDb>)> {
+    let mut mat_db = MaterialDb::new();
+    mat_db.load_ron_file(&paths.materials).context("failed to load materials")?;
+    let mut react_db = ReactionDb::new();
+    react_db.load_ron_file(&mat_db, &paths.reactions).context("failed to load reactions")?;
+    Ok((Arc::new(mat_db), Arc::new(react_db)))
+}
+
 /// Helper for loading map state from a bitmap and a RON file.
 /// A given hex code on the bitmap represents a mat_id and temp.
 #[derive(serde::Deserialize)]
@@ -127,111 +227,127 @@ struct MapEntry {
     temperature: f32,
 }
 
-/// Builds world and physics engine.
-pub fn build_world_and_engine(config: HashMap<String, Value>, w: usize, h: usize, mat_db: &Arc<MaterialDb>, react_db: &Arc<ReactionDb>) -> (World, Engine) {
-    let mut world = World::new(w, h, mat_db, react_db);
-    let mut phys_eng = Engine::new(config, w, h);
+/// Paint a bitmap map onto the world. `map_key` is a RON file that assigns a material and
+/// temperature to each color hex code. Pixels past the world edge or not in the key are left
+/// alone. Fails if either file cannot be read, or the key names a missing material.
+pub fn load_map(world: &mut World, map: &str, map_key: &str) -> Result<()> {
+    // We make a map of hex codes to map entries, then read the bitmap and assign.
+    let key_text = fs::read_to_string(map_key).with_context(|| format!("failed to read map key {}", map_key))?;
+    let key_raw: HashMap<String, MapEntry> = ron::de::from_str(&key_text).with_context(|| format!("failed to parse map key {}", map_key))?;
+    // Convert hex codes to uppercase so user can't get it wrong.
+    let mut key = HashMap::new();
+    for (hex, entry) in key_raw {
+        let mat_id = world.mat_db.get_id(&entry.material).with_context(|| format!("Missing material: {} (in map key {})", entry.material, map_key))?;
+        key.insert(hex.to_uppercase(), (mat_id, entry.temperature));
+    }
+    let map_img = image::open(map).with_context(|| format!("failed to open map {}", map))?;
+    let (img_w, img_h) = map_img.dimensions();
+
+    // Clamp map size to world size.
+    world.sync_all();
+    let (w, h) = (world.w, world.h);
+    let (_, mut next) = world.ctx_pair();
+    for y in 0..img_h.min(h as u32) {
+        for x in 0..img_w.min(w as u32) {
+            let p = map_img.get_pixel(x, y);
+            let hex = format!("#{:02X}{:02X}{:02X}", p[0], p[1], p[2]);
+            if let Some(&(mat_id, temp)) = key.get(&hex) {
+                next.set_mat_id(x as usize, y as usize, mat_id);
+                next.set_temp(x as usize, y as usize, temp);
+            }
+        }
+    }
+    world.swap_all();
+    Ok(())
+}
 
-    let base_seed = 123456789u64;
-    // let mut global_rng = Xoshiro256PlusPlus::seed_from_u64(base_seed);
+/// Builds world and physics engine. The world starts as `empty_material` from config, with
+/// the map from `paths.map` and `paths.map_key` painted on if there is one. Every module RNG
+/// is derived from `base_seed`. Fails if config is invalid, or the map cannot be loaded.
+pub fn build_world_and_engine(config: HashMap<String, Value>, w: usize, h: usize, mat_db: &Arc<MaterialDb>, react_db: &Arc<ReactionDb>, paths: &AssetPaths, base_seed: u64) -> Result<(World, Engine)> {
+    let mut world = World::new(w, h, mat_db, react_db);
+    world.boundary = Boundary::from_config(&config, mat_db).context("failed to load boundary config")?;
+    let mut phys_eng = Engine::new(config, mat_db, w, h).context("failed to load engine config")?;
 
-    // Basic bitmap-based map loading for demo purposes.
     {
-        let (curr, mut next) = world.ctx_pair();
-
-        // Initialize the world before loading the png map.
+        let (_, mut next) = world.ctx_pair();
         for y in 0..h {
             for x in 0..w {
-                next.set_mat_id(x, y, curr.mat_db.get_id("base:air").expect("Missing material: base:air"));
+                next.set_mat_id(x, y, phys_eng.empty_material());
                 next.set_temp(x, y, 50.0);
             }
         }
-
-        // The RON file assigns a mat_id and temp to a given color hex code.
-        // We make a map of hex codes to map entries, then read the bitmap and assign.
-        if let Ok(key_text) = fs::read_to_string("assets/map_key.ron") {
-            if let Ok(mut map_key_raw) = ron::de::from_str::<HashMap<String, MapEntry>>(&key_text) {
-                // Convert hex codes to uppercase so user can't get it wrong.
-                let map_key = map_key_raw
-                    .drain()
-                    .map(|(k, v)| (k.to_uppercase(), v))
-                    .collect::<HashMap<String, MapEntry>>();
-                if let Ok(map_img) = image::open("assets/map.png") {
-                    let (img_w, img_h) = map_img.dimensions();
-
-                    // Clamp map size to world size.
-                    for y in 0..img_h.min(h as u32) {
-                        for x in 0..img_w.min(w as u32) {
-                            let p = map_img.get_pixel(x, y);
-                            let hex = format!("#{:02X}{:02X}{:02X}", p[0], p[1], p[2]);
-                            if let Some(entry) = map_key.get(&hex) {
-                                if let Some(mat_id) = mat_db.get_id(&entry.material) {
-                                    next.set_mat_id(x as usize, y as usize, mat_id);
-                                    next.set_temp(x as usize, y as usize, entry.temperature);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        world.swap_all();
+    }
+    world.swap_all();
+    if let Some(map) = &paths.map {
+        load_map(&mut world, map, &paths.map_key)?;
     }
 
     // Physics modules
     {
-        let (curr, mut next) = world.ctx_pair();
+        let (curr, _) = world.ctx_pair();
 
         // Modules are applied in the order they are added. Modules should be okay to run in any order.
         // However, by necessity it usually makes sense to run them in the following three stages:
 
         // Stage 1. Things that modify the state (i.e. temperature) of cells.
         phys_eng.add(ModuleDiffusionThermal::new(&curr,     base_seed ^ 0x0FEDCBA123456789));
+        phys_eng.add(ModuleThermostat::new(&curr));
+        phys_eng.add(ModuleCombustionHeat::new(&curr));
+        phys_eng.add(ModuleRadiation::new(&curr,           base_seed ^ 0xA54FF53A5F1D36F1));
+        phys_eng.add(ModulePressure::new(&curr));
+        phys_eng.add(ModuleFluid::new(&curr));
 
         // Stage 2. Things that change the material of the cell.
+        // Explosions go first, so a detonation is not blocked by cells that other modules changed this tick.
+        phys_eng.add(ModuleExplosion::new(&curr, phys_eng.empty_material(), base_seed ^ 0x510E527FADE682D1));
+        phys_eng.add(ModuleCombustion::new(&curr,           base_seed ^ 0x9B05688C2B3E6C1F));
         phys_eng.add(ModuleTransformsThermal::new(&curr,    base_seed ^ 0x345289A01DEFCB67));
+        phys_eng.add(ModuleTransformsDecay::new(&curr,      base_seed ^ 0xD1B54A32D192ED03));
         phys_eng.add(ModuleReactionsBasic::new(&curr,       base_seed ^ 0x0123456789ABCDEF));
 
         // Stage 3. Things that move cell contents around.
         // Cell swap intents should be applied last, because they usually want to swap state that was modified by other modules.
-        // For instance, a moving steam particle should carry its temp with it, including changes to that temp this tick.
+        // For instance, a rising steam particle should carry its temp with it, including changes to that temp this tick.
         // So we let all the thermal diffusion occur, then move the 'particle', so it can be ready for diffusion next frame.
         // To do so, it needs to swap the already modified values in next buffer.
-        phys_eng.add(ModuleBehaviorSteam::new(&curr,        base_seed ^ 0xF0E1D2C3B4A59687));
+        phys_eng.add(ModuleGravity::new(&curr,              base_seed ^ 0x6A09E667F3BCC908));
+        phys_eng.add(ModuleFluidFlow::new(&curr,            base_seed ^ 0x3C6EF372FE94F82B));
+        phys_eng.add(ModuleGas::new(&curr,                  base_seed ^ 0xF0E1D2C3B4A59687));
     }
-    (world, phys_eng)
+    Ok((world, phys_eng))
 }
 
 /// Loads DBs, builds World and Phys Engine, starts the Sim thread, and
 /// returns a handle to the Shared data struct for the Render thread.
-pub fn spawn_sim_thread(config: HashMap<String, Value>, w: usize, h: usize) -> Arc<Shared> {
-    let mat_db = {
-        let mut mdb = MaterialDb::new();
-        mdb
-            .load_ron_file("assets/materials_base.ron")
-            .expect("failed to load materials");
-        Arc::new(mdb)
-    };
-    let react_db = {
-        let mut rdb = ReactionDb::new();
-        (rdb)
-            .load_ron_file(&mat_db, "assets/reactions_base.ron")
-            .expect("failed to load reactions");
-        Arc::new(rdb)
-    };
+/// The Sim thread ticks at `target_tps` from config and is controlled via `Shared::send_control`.
+/// It stops once every `Arc<Shared>` has been dropped.
+/// Fails if the databases or the map cannot be loaded, or `target_tps` is invalid.
+pub fn spawn_sim_thread(config: HashMap<String, Value>, w: usize, h: usize, paths: AssetPaths, seed: u64) -> Result<Arc<Shared>> {
+    let (mat_db, react_db) = load_dbs(&paths)?;
     let initial = Arc::new(Snapshot {
         w,
         h,
         cell_mat_ids: vec![MaterialId(0); w * h].into_boxed_slice(),
         cell_temps: vec![0.0f32; w * h].into_boxed_slice(),
     });
-    let shared = Shared::new(initial, mat_db, react_db);
+
+    let target_tps = config.get("target_tps")
+        .map(|v| v.as_f64().context("Invalid config: target_tps must be f64"))
+        .transpose()?
+        .unwrap_or(DEFAULT_TARGET_TPS);
+    ensure!(target_tps.is_finite() && target_tps >= 0.0, "Invalid config: target_tps must be finite and not negative, got {}", target_tps);
+    let mut clock = SimClock::new(target_tps);
+
+    let (message_tx, message_rx) = mpsc::channel();
+    let shared = Shared::new(initial, clock.status(), mat_db, react_db, message_tx);
+    let (mut world, mut phys_eng) = build_world_and_engine(config, w, h, &shared.mat_db, &shared.react_db, &paths, seed)?;
 
     std::thread::spawn({
-        let shared = Arc::clone(&shared);
+        let weak = Arc::downgrade(&shared);
         move || {
 
-            let publish = |world: &World| {
+            let publish = |shared: &Shared, world: &World| {
                 let snap = Snapshot {
                     w: world.w,
                     h: world.h,
@@ -241,24 +357,158 @@ pub fn spawn_sim_thread(config: HashMap<String, Value>, w: usize, h: usize) -> A
                 shared.current.store(Arc::new(snap));
             };
 
-            let (mut world, mut phys_eng) = build_world_and_engine(config, w, h, &shared.mat_db, &shared.react_db);
-
-            publish(&world);
+            match weak.upgrade() {
+                Some(shared) => publish(&shared, &world),
+                None => return,
+            }
 
+            let mut last = Instant::now();
             loop {
-                phys_eng.step(&mut world);
-                shared.tick_count.fetch_add(1, Ordering::Relaxed);
-                publish(&world);
+                // Sleep until the next tick is due or a message arrives, whichever is first.
+                let msg = match clock.time_until_next_tick() {
+                    None => message_rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    Some(wait) if wait.is_zero() => match message_rx.try_recv() {
+                        Err(TryRecvError::Empty) => Err(RecvTimeoutError::Timeout),
+                        other => other.map_err(|_| RecvTimeoutError::Disconnected),
+                    },
+                    Some(wait) => message_rx.recv_timeout(wait),
+                };
+                let msg = match msg {
+                    Ok(msg) => Some(msg),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return, // Every `Shared` is gone.
+                };
+                let Some(shared) = weak.upgrade() else { return };
+                if let Some(msg) = msg {
+                    handle_messages(&mut clock, &mut phys_eng, msg, &message_rx, &shared);
+                }
+
+                let now = Instant::now();
+                let due = clock.ticks_due(now - last);
+                last = now;
+
+                for _ in 0..due {
+                    phys_eng.step(&mut world);
+                    shared.tick_count.store(world.tick_count, Ordering::Relaxed);
+                }
+                if due > 0 {
+                    publish(&shared, &world);
+                } else if clock.status().paused && phys_eng.has_commands() {
+                    // No tick is coming, so show edits made while paused right away.
+                    phys_eng.apply_commands(&mut world);
+                    publish(&shared, &world);
+                }
             }
         }
     });
 
-    shared
+    Ok(shared)
+}
+
+/// Handle a message, plus any others already queued. Clock commands are applied and the new
+/// clock status published, and world edits are queued on the engine.
+fn handle_messages(clock: &mut SimClock, phys_eng: &mut Engine, first: SimMessage, message_rx: &Receiver<SimMessage>, shared: &Shared) {
+    for msg in std::iter::once(first).chain(message_rx.try_iter()) {
+        match msg {
+            SimMessage::Control(cmd) => clock.apply(cmd),
+            SimMessage::Command(cmd) => phys_eng.queue_command(cmd),
+        }
+    }
+    shared.clock.store(Arc::new(clock.status()));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::physics::command::Region;
+    use std::time::Duration;
+
+    /// Poll until `cond` holds, failing the test after a generous timeout.
+    fn wait_for(mut cond: impl FnMut() -> bool) {
+        let start = Instant::now();
+        while !cond() {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out waiting for sim thread");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_sim_thread_pause_and_step() {
+        let mut config = HashMap::new();
+        config.insert("target_tps".to_owned(), Value::from(0.0));
+        let shared = spawn_sim_thread(config, 16, 16, AssetPaths::default(), DEFAULT_SEED).unwrap();
+
+        // Unlimited rate: ticks advance on their own.
+        wait_for(|| shared.tick_count.load(Ordering::Relaxed) > 3);
+
+        shared.send_control(SimControl::Pause);
+        wait_for(|| shared.clock.load().paused);
+        let paused_at = shared.tick_count.load(Ordering::Relaxed);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(shared.tick_count.load(Ordering::Relaxed), paused_at);
+
+        // Single step while paused runs exactly one tick.
+        shared.send_control(SimControl::StepOnce);
+        wait_for(|| shared.tick_count.load(Ordering::Relaxed) == paused_at + 1);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(shared.tick_count.load(Ordering::Relaxed), paused_at + 1);
+    }
+
+    #[test]
+    fn test_sim_thread_applies_commands_while_paused() {
+        let shared = spawn_sim_thread(HashMap::new(), 16, 16, AssetPaths::default(), DEFAULT_SEED).unwrap();
+        shared.send_control(SimControl::Pause);
+        wait_for(|| shared.clock.load().paused);
+        let paused_at = shared.tick_count.load(Ordering::Relaxed);
+
+        // The edit shows up without a tick.
+        let stone = shared.mat_db.get_id("base:stone").unwrap();
+        assert_ne!(shared.current.load().mat_id_at(3, 4), stone);
+        shared.send_command(WorldCommand::PlaceMaterial { region: Region::Cell { x: 3, y: 4 }, mat: stone, temp: None });
+        wait_for(|| shared.current.load().mat_id_at(3, 4) == stone);
+        assert_eq!(shared.tick_count.load(Ordering::Relaxed), paused_at);
+    }
+
+    #[test]
+    fn test_sim_thread_stops_when_shared_dropped() {
+        let shared = spawn_sim_thread(HashMap::new(), 16, 16, AssetPaths::default(), DEFAULT_SEED).unwrap();
+        shared.send_control(SimControl::Pause);
+        wait_for(|| shared.clock.load().paused);
+
+        // The thread owns the world, which holds the material database until the thread ends.
+        let mat_db = Arc::clone(&shared.mat_db);
+        drop(shared);
+        wait_for(|| Arc::strong_count(&mat_db) == 1);
+    }
+
+    #[test]
+    fn test_invalid_target_tps_is_an_error() {
+        for tps in [Value::from("fast"), Value::from(-1.0), Value::from(f64::INFINITY)] {
+            let mut config = HashMap::new();
+            config.insert("target_tps".to_owned(), tps);
+            assert!(spawn_sim_thread(config, 8, 8, AssetPaths::default(), DEFAULT_SEED).is_err());
+        }
+    }
+
+    #[test]
+    fn test_build_without_map_is_empty() {
+        let (mat_db, react_db) = load_dbs(&AssetPaths::default()).unwrap();
+        let paths = AssetPaths { map: None, map_key: "assets/no_such_key.ron".to_owned(), ..AssetPaths::default() };
+        let (world, engine) = build_world_and_engine(HashMap::new(), 8, 8, &mat_db, &react_db, &paths, DEFAULT_SEED).unwrap();
+        assert!(world.cell_mat_ids.cur.iter().all(|&id| id == engine.empty_material()));
+    }
+
+    #[test]
+    fn test_missing_map_is_an_error() {
+        let (mat_db, react_db) = load_dbs(&AssetPaths::default()).unwrap();
+        let build = |paths: &AssetPaths| build_world_and_engine(HashMap::new(), 8, 8, &mat_db, &react_db, paths, DEFAULT_SEED);
+        assert!(build(&AssetPaths::default()).is_ok());
+
+        let paths = AssetPaths { map: Some("assets/no_such_map.png".to_owned()), ..AssetPaths::default() };
+        assert!(build(&paths).is_err());
+        let paths = AssetPaths { map_key: "assets/no_such_key.ron".to_owned(), ..AssetPaths::default() };
+        assert!(build(&paths).is_err());
+    }
 
     #[test]
     fn test_double_buffer_sync() {
@@ -1,5 +1,7 @@
-﻿use std::sync::Arc;
-use crate::material::{MaterialDb, MaterialId};
+﻿use std::collections::BTreeMap;
+use std::sync::Arc;
+use crate::boundary::{Boundary, Neighbor};
+use crate::material::{MaterialDb, MaterialId, Thermostat};
 use crate::reaction::ReactionDb;
 use crate::sim::{DoubleBuffer, Entity};
 
@@ -7,10 +9,38 @@ pub struct World {
     pub w: usize,
     pub h: usize,
 
+    /// Number of ticks the engine has stepped this world.
+    pub tick_count: u64,
+
+    /// What lies past each edge of the world. Insulating walls unless set from config.
+    pub boundary: Boundary,
+
     pub cell_mat_ids: DoubleBuffer<Vec<MaterialId>>,
     pub cell_temps: DoubleBuffer<Vec<f32>>,
+    /// Ticks since each cell became its current material. Moves carry the age along.
+    pub cell_ages: DoubleBuffer<Vec<u32>>,
+    /// Latent heat each cell has stored toward a phase transition, in degrees. Positive is
+    /// stored toward the hot transform, negative toward the cold one. Moves carry it along.
+    pub cell_latent: DoubleBuffer<Vec<f32>>,
+    /// Material each fire cell is burning. Only meaningful for fire cells. Moves carry it along.
+    pub cell_fuel: DoubleBuffer<Vec<MaterialId>>,
+    /// Amount of gas in each cell, where 1.0 fills a cell at reference pressure and 20°C. Only
+    /// meaningful for gas cells. Moves carry it along.
+    pub cell_gas_mass: DoubleBuffer<Vec<f32>>,
+    /// Fluid velocity across the east face of each cell, in cells per tick. The last column
+    /// holds the face that wraps around to the first column. Belongs to the grid rather than
+    /// the material, so moves do not carry it along. Zero across faces that do not join two
+    /// liquid or gas cells.
+    pub cell_vel_x: DoubleBuffer<Vec<f32>>,
+    /// Fluid velocity across the south face of each cell, laid out like `cell_vel_x`.
+    pub cell_vel_y: DoubleBuffer<Vec<f32>>,
     pub entities: DoubleBuffer<Vec<Entity>>,
 
+    /// Cells marked as thermostatic sources, by cell index. A marked cell keeps its thermostat
+    /// whatever material moves through it, and overrides any thermostat of that material.
+    /// Only changed between ticks, so it is not double-buffered.
+    pub cell_sources: BTreeMap<usize, Thermostat>,
+
     pub mat_db: Arc<MaterialDb>,
     pub react_db: Arc<ReactionDb>,
 }
@@ -19,21 +49,51 @@ impl World {
     pub fn new(w: usize, h: usize, mat_db: &Arc<MaterialDb>, react_db: &Arc<ReactionDb>) -> Self {
         let cell_mat_ids = vec![MaterialId(0); w * h];
         let cell_temps = vec![0.0f32; w * h];
+        let cell_ages = vec![0u32; w * h];
+        let cell_latent = vec![0.0f32; w * h];
+        let cell_fuel = vec![MaterialId(0); w * h];
+        let cell_gas_mass = vec![1.0f32; w * h];
+        let cell_vel_x = vec![0.0f32; w * h];
+        let cell_vel_y = vec![0.0f32; w * h];
         let entities = vec![Entity::empty(); w * h];
 
         Self {
             w, h,
+            tick_count: 0,
+            boundary: Boundary::default(),
             cell_mat_ids: DoubleBuffer::new(cell_mat_ids),
             cell_temps: DoubleBuffer::new(cell_temps),
+            cell_ages: DoubleBuffer::new(cell_ages),
+            cell_latent: DoubleBuffer::new(cell_latent),
+            cell_fuel: DoubleBuffer::new(cell_fuel),
+            cell_gas_mass: DoubleBuffer::new(cell_gas_mass),
+            cell_vel_x: DoubleBuffer::new(cell_vel_x),
+            cell_vel_y: DoubleBuffer::new(cell_vel_y),
             entities: DoubleBuffer::new(entities),
+            cell_sources: BTreeMap::new(),
             mat_db: Arc::clone(mat_db),
             react_db: Arc::clone(react_db),
         }
     }
 
+    /// Mark a cell as a thermostatic source, or unmark it with None. Cells outside the world are ignored.
+    pub fn set_source(&mut self, x: usize, y: usize, source: Option<Thermostat>) {
+        if (!contains(self.w, self.h, x, y)) { return; }
+        match source {
+            Some(source) => self.cell_sources.insert(index(self.w, x, y), source),
+            None => self.cell_sources.remove(&index(self.w, x, y)),
+        };
+    }
+
     pub fn sync_all(&mut self) {
         self.cell_mat_ids.sync();
         self.cell_temps.sync();
+        self.cell_ages.sync();
+        self.cell_latent.sync();
+        self.cell_fuel.sync();
+        self.cell_gas_mass.sync();
+        self.cell_vel_x.sync();
+        self.cell_vel_y.sync();
         self.entities.sync();
     }
 
@@ -42,6 +102,12 @@ impl World {
     pub fn swap_all(&mut self) {
         self.cell_mat_ids.swap();
         self.cell_temps.swap();
+        self.cell_ages.swap();
+        self.cell_latent.swap();
+        self.cell_fuel.swap();
+        self.cell_gas_mass.swap();
+        self.cell_vel_x.swap();
+        self.cell_vel_y.swap();
         self.entities.swap();
     }
 
@@ -49,9 +115,17 @@ impl World {
         let curr = CurrCtx {
             w: self.w,
             h: self.h,
+            boundary: &self.boundary,
             cell_mat_ids: &self.cell_mat_ids.cur,
             cell_temps: &self.cell_temps.cur,
+            cell_ages: &self.cell_ages.cur,
+            cell_latent: &self.cell_latent.cur,
+            cell_fuel: &self.cell_fuel.cur,
+            cell_gas_mass: &self.cell_gas_mass.cur,
+            cell_vel_x: &self.cell_vel_x.cur,
+            cell_vel_y: &self.cell_vel_y.cur,
             entities: &self.entities.cur,
+            cell_sources: &self.cell_sources,
             mat_db: &self.mat_db,
             react_db: &self.react_db,
         };
@@ -60,6 +134,12 @@ impl World {
             h: self.h,
             cell_mat_ids: &mut self.cell_mat_ids.next,
             cell_temps: &mut self.cell_temps.next,
+            cell_ages: &mut self.cell_ages.next,
+            cell_latent: &mut self.cell_latent.next,
+            cell_fuel: &mut self.cell_fuel.next,
+            cell_gas_mass: &mut self.cell_gas_mass.next,
+            cell_vel_x: &mut self.cell_vel_x.next,
+            cell_vel_y: &mut self.cell_vel_y.next,
             entities: &mut self.entities.next,
         };
         (curr, next)
@@ -93,9 +173,17 @@ impl World {
 pub struct CurrCtx<'a> {
     pub w: usize,
     pub h: usize,
+    pub boundary: &'a Boundary,
     pub cell_mat_ids: &'a [MaterialId],
     pub cell_temps: &'a [f32],
+    pub cell_ages: &'a [u32],
+    pub cell_latent: &'a [f32],
+    pub cell_fuel: &'a [MaterialId],
+    pub cell_gas_mass: &'a [f32],
+    pub cell_vel_x: &'a [f32],
+    pub cell_vel_y: &'a [f32],
     pub entities: &'a [Entity],
+    pub cell_sources: &'a BTreeMap<usize, Thermostat>,
     pub mat_db: &'a MaterialDb,
     pub react_db: &'a ReactionDb,
 }
@@ -125,9 +213,53 @@ impl<'a> CurrCtx<'a> {
         self.cell_temps[i]
     }
 
+    #[inline] pub fn get_age(&self, x: usize, y: usize) -> u32 {
+        self.cell_ages[index(self.w, x, y)]
+    }
+
+    #[inline] pub fn get_latent(&self, x: usize, y: usize) -> f32 {
+        self.cell_latent[index(self.w, x, y)]
+    }
+
+    #[inline] pub fn get_fuel(&self, x: usize, y: usize) -> MaterialId {
+        self.cell_fuel[index(self.w, x, y)]
+    }
+
+    #[inline] pub fn get_gas_mass(&self, x: usize, y: usize) -> f32 {
+        self.cell_gas_mass[index(self.w, x, y)]
+    }
+
+    /// Gas pressure of the cell at (x, y), relative to reference pressure. Only meaningful for gas cells.
+    #[inline] pub fn get_pressure(&self, x: usize, y: usize) -> f32 {
+        let i = index(self.w, x, y);
+        gas_pressure(self.cell_gas_mass[i], self.cell_temps[i])
+    }
+
+    /// Fluid velocity at the center of (x, y), in cells per tick, averaged from its faces.
+    #[inline] pub fn get_vel(&self, x: usize, y: usize) -> (f32, f32) {
+        let i = index(self.w, x, y);
+        let west = index(self.w, if (x == 0) { self.w - 1 } else { x - 1 }, y);
+        let north = index(self.w, x, if (y == 0) { self.h - 1 } else { y - 1 });
+        (0.5 * (self.cell_vel_x[west] + self.cell_vel_x[i]), 0.5 * (self.cell_vel_y[north] + self.cell_vel_y[i]))
+    }
+
+    /// True if (x, y) is inside the world. Does not wrap; use `neighbor` to look across edges.
     pub fn contains(&self, x: isize, y: isize) -> bool {
         contains(self.w, self.h, x as usize, y as usize)
     }
+
+    /// What lies at (x + dx, y + dy), wrapping around periodic edges.
+    #[inline] pub fn neighbor(&self, x: usize, y: usize, dx: isize, dy: isize) -> Neighbor {
+        self.boundary.neighbor(self.w, self.h, x, y, dx, dy)
+    }
+
+    /// The cell at (x + dx, y + dy), wrapping around periodic edges. None past walls and voids.
+    #[inline] pub fn neighbor_cell(&self, x: usize, y: usize, dx: isize, dy: isize) -> Option<(usize, usize)> {
+        match self.neighbor(x, y, dx, dy) {
+            Neighbor::Cell(nx, ny) => Some((nx, ny)),
+            Neighbor::Wall | Neighbor::Void => None,
+        }
+    }
 }
 
 
@@ -136,9 +268,17 @@ impl<'a> CurrCtx<'a> {
 
 pub struct NextCtx<'a> {
     w: usize,
+    #[allow(dead_code)]
     h: usize,
     cell_mat_ids: &'a mut Vec<MaterialId>,
     cell_temps: &'a mut Vec<f32>,
+    cell_ages: &'a mut Vec<u32>,
+    cell_latent: &'a mut Vec<f32>,
+    cell_fuel: &'a mut Vec<MaterialId>,
+    cell_gas_mass: &'a mut Vec<f32>,
+    cell_vel_x: &'a mut Vec<f32>,
+    cell_vel_y: &'a mut Vec<f32>,
+    #[allow(dead_code)]
     entities: &'a mut Vec<Entity>,
 }
 
@@ -147,6 +287,10 @@ impl<'a> NextCtx<'a> {
         self.cell_mat_ids[index(self.w, x, y)] = material_id;
     }
 
+    #[inline] pub fn peek_future_mat_id(&self, x: usize, y: usize) -> MaterialId {
+        self.cell_mat_ids[index(self.w, x, y)]
+    }
+
     #[inline] pub fn set_temp(&mut self, x: usize, y: usize, temp: f32) {
         self.cell_temps[index(self.w, x, y)] = temp;
     }
@@ -168,6 +312,67 @@ impl<'a> NextCtx<'a> {
     #[inline] pub fn peek_future_temp(&self, x: usize, y: usize) -> f32 {
         self.cell_temps[index(self.w, x, y)]
     }
+
+    #[inline] pub fn set_age(&mut self, x: usize, y: usize, age: u32) {
+        self.cell_ages[index(self.w, x, y)] = age;
+    }
+
+    #[inline] pub fn peek_future_age(&self, x: usize, y: usize) -> u32 {
+        self.cell_ages[index(self.w, x, y)]
+    }
+
+    #[inline] pub fn set_latent(&mut self, x: usize, y: usize, latent: f32) {
+        self.cell_latent[index(self.w, x, y)] = latent;
+    }
+
+    #[inline] pub fn peek_future_latent(&self, x: usize, y: usize) -> f32 {
+        self.cell_latent[index(self.w, x, y)]
+    }
+
+    #[inline] pub fn set_fuel(&mut self, x: usize, y: usize, fuel: MaterialId) {
+        self.cell_fuel[index(self.w, x, y)] = fuel;
+    }
+
+    #[inline] pub fn peek_future_fuel(&self, x: usize, y: usize) -> MaterialId {
+        self.cell_fuel[index(self.w, x, y)]
+    }
+
+    #[inline] pub fn set_gas_mass(&mut self, x: usize, y: usize, mass: f32) {
+        self.cell_gas_mass[index(self.w, x, y)] = mass;
+    }
+
+    /// Uses flattened index, which is sometimes faster than converting to 2D and back.
+    #[inline] pub fn add_gas_mass_i(&mut self, i: usize, mass: f32) {
+        self.cell_gas_mass[i] += mass;
+    }
+
+    #[inline] pub fn peek_future_gas_mass(&self, x: usize, y: usize) -> f32 {
+        self.cell_gas_mass[index(self.w, x, y)]
+    }
+
+    /// Add to the velocity across the east and south faces of a cell.
+    /// Uses flattened index, which is sometimes faster than converting to 2D and back.
+    #[inline] pub fn add_vel_i(&mut self, i: usize, vx: f32, vy: f32) {
+        self.cell_vel_x[i] += vx;
+        self.cell_vel_y[i] += vy;
+    }
+
+    /// Reset state that belonged to the cell's previous material, when it becomes a new one.
+    /// A cell that becomes a gas starts with one cell's worth of gas, so boiling a liquid in a
+    /// sealed space raises its pressure.
+    #[inline] pub fn reset_cell_state(&mut self, x: usize, y: usize) {
+        self.set_age(x, y, 0);
+        self.set_latent(x, y, 0.0);
+        self.set_fuel(x, y, MaterialId(0));
+        self.set_gas_mass(x, y, 1.0);
+    }
+
+    /// Advance every cell's age by one tick.
+    pub fn age_all(&mut self) {
+        for age in self.cell_ages.iter_mut() {
+            *age = age.saturating_add(1);
+        }
+    }
 }
 
 // ------------------------------- POST RUN CONTEXT -------------------------------
@@ -184,6 +389,18 @@ pub struct PostRunCtx<'a> {
 }
 
 // -------------------------------- UTILITIES --------------------------------
+/// Offset from Celsius to Kelvin.
+pub const KELVIN: f32 = 273.15;
+/// Temperature at which a gas has its material `density`, and one cell's worth of gas is at
+/// reference pressure.
+pub const GAS_REFERENCE_TEMP: f32 = 20.0;
+
+/// Pressure of `mass` cells' worth of gas at `temp`, relative to reference pressure. Follows
+/// the ideal gas law, so heating a gas that cannot expand raises its pressure.
+#[inline] pub fn gas_pressure(mass: f32, temp: f32) -> f32 {
+    mass * (temp + KELVIN).max(1.0) / (GAS_REFERENCE_TEMP + KELVIN)
+}
+
 /// Convert a 2D index to 1D.
 #[inline] fn index(w: usize, x: usize, y: usize) -> usize { y * w + x }
 